@@ -0,0 +1,74 @@
+//! Compares [`ArcFile::extract_dir`] on an arc opened using [`ArcFile::from_reader`], which
+//! reads through a shared mutex-guarded reader, and on one opened using [`ArcFile::open`], which
+//! reads the file positionally without locking, with [`ArcFile::extract_dir_parallel`].
+//!
+//! Requires a real data.arc, configured through environment variables:
+//!
+//! * `SMASH_ARC_BENCH_ARC` - path to the data.arc
+//! * `SMASH_ARC_BENCH_LABELS` - path to the hash labels file
+//! * `SMASH_ARC_BENCH_DIR` - directory to extract (default: `fighter/mario`)
+
+use std::env;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::PathBuf;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use smash_arc::{ArcFile, Hash40, Region};
+
+fn extract(c: &mut Criterion) {
+    let (arc_path, labels) = match (
+        env::var_os("SMASH_ARC_BENCH_ARC"),
+        env::var_os("SMASH_ARC_BENCH_LABELS"),
+    ) {
+        (Some(arc), Some(labels)) => (PathBuf::from(arc), labels),
+        _ => {
+            eprintln!("SMASH_ARC_BENCH_ARC and SMASH_ARC_BENCH_LABELS must be set, skipping");
+            return;
+        }
+    };
+    let dir = env::var("SMASH_ARC_BENCH_DIR").unwrap_or_else(|_| String::from("fighter/mario"));
+
+    Hash40::set_global_labels_file(labels).unwrap();
+    let mutex_arc = ArcFile::from_reader(BufReader::new(File::open(&arc_path).unwrap())).unwrap();
+    let arc = ArcFile::open(&arc_path).unwrap();
+    let dest = env::temp_dir().join("smash-arc-bench");
+
+    let mut group = c.benchmark_group("extract_dir");
+    group.sample_size(10);
+
+    group.bench_function("mutex", |b| {
+        b.iter(|| {
+            mutex_arc
+                .extract_dir(&dir[..], &dest, Region::UsEnglish, Default::default())
+                .unwrap()
+        })
+    });
+
+    group.bench_function("read_at", |b| {
+        b.iter(|| {
+            arc.extract_dir(&dir[..], &dest, Region::UsEnglish, Default::default())
+                .unwrap()
+        })
+    });
+
+    group.bench_function("parallel", |b| {
+        b.iter(|| {
+            arc.extract_dir_parallel(
+                &dir[..],
+                &dest,
+                Region::UsEnglish,
+                Default::default(),
+                || File::open(&arc_path).map(BufReader::new),
+            )
+            .unwrap()
+        })
+    });
+
+    group.finish();
+
+    let _ = fs::remove_dir_all(&dest);
+}
+
+criterion_group!(benches, extract);
+criterion_main!(benches);
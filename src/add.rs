@@ -0,0 +1,728 @@
+use std::convert::TryFrom;
+
+use crate::hash40::hash40;
+use crate::replace::too_large;
+use crate::{
+    ArcFile, ArcLookup, Compression, DirInfo, DirInfoFlags, DirectoryOffset, FileData,
+    FileDataFlags, FileDataIdx, FileInfo, FileInfoFlags, FileInfoIdx, FileInfoIndex,
+    FileInfoIndiceIdx, FileInfoToFileData, FileInfoToFileDataBitfield, FilePath, FilePathIdx,
+    FileSystem, Hash40, HashToIndex, InfoToDataIdx, LookupError, PathListEntry, SearchFileSystem,
+    SearchListEntry, SearchLookup,
+};
+
+/// Alignment of the folder offset given to directories created by [`ArcFile::add_dir`]
+const NEW_FOLDER_ALIGNMENT: u64 = 0x10;
+
+/// Split a path into its parent directory and its final component
+fn split_path(path: &str) -> Result<(&str, &str), LookupError> {
+    path.rsplit_once('/').ok_or(LookupError::InvalidPath)
+}
+
+/// Split a directory path into the hash of its parent directory (if it isn't a top level
+/// directory) and its final component. Top level directories have an empty parent hash.
+fn split_dir_path(path: &str) -> Result<(Option<&str>, Hash40, &str), LookupError> {
+    match path.rsplit_once('/') {
+        Some((parent, name)) => Ok((Some(parent), hash40(parent), name)),
+        None if !path.is_empty() => Ok((None, Hash40::from(0), path)),
+        None => Err(LookupError::InvalidPath),
+    }
+}
+
+fn extension(file_name: &str) -> &str {
+    file_name
+        .rsplit_once('.')
+        .map(|(_, ext)| ext)
+        .unwrap_or_default()
+}
+
+impl FileSystem {
+    fn dir_info_index(&self, hash: Hash40) -> Option<usize> {
+        self.dir_hash_to_info_index
+            .binary_search_by_key(&hash, HashToIndex::hash40)
+            .ok()
+            .map(|index| self.dir_hash_to_info_index[index].index() as usize)
+    }
+
+    /// Add a new, empty directory whose files will be stored relative to `folder_offset` in the
+    /// file section, along with any missing parent directories up to the top level. Each
+    /// directory is added to the children of its parent.
+    pub fn add_dir(&mut self, path: &str, folder_offset: u64) -> Result<(), LookupError> {
+        let (parent_path, parent, name) = split_dir_path(path)?;
+        let hash = hash40(path);
+
+        if self.dir_info_index(hash).is_some() {
+            return Err(LookupError::AlreadyExists);
+        }
+
+        if let Some(parent_path) = parent_path {
+            if self.dir_info_index(parent).is_none() {
+                self.add_dir(parent_path, folder_offset)?;
+            }
+        }
+
+        let insert_at = self
+            .dir_hash_to_info_index
+            .binary_search_by_key(&hash, HashToIndex::hash40)
+            .unwrap_err();
+
+        let dir_index = self.dir_infos.len() as u32;
+        let folder_offset_index = self.folder_offsets.len() as u32;
+
+        self.folder_offsets.push(DirectoryOffset {
+            offset: folder_offset,
+            decomp_size: 0,
+            size: 0,
+            file_start_index: self.file_datas.len() as u32,
+            file_count: 0,
+            directory_index: 0xFF_FFFF,
+        });
+        self.fs_header.extra_folder += 1;
+
+        self.dir_infos.push(DirInfo {
+            path: HashToIndex::from_hash40(hash, folder_offset_index),
+            name: hash40(name),
+            parent,
+            extra_dis_re: 0,
+            extra_dis_re_length: 0,
+            file_info_start_index: self.file_infos.len() as u32,
+            file_count: 0,
+            child_dir_start_index: self.folder_child_hashes.len() as u32,
+            child_dir_count: 0,
+            flags: DirInfoFlags::new(),
+        });
+        self.dir_hash_to_info_index
+            .insert(insert_at, HashToIndex::from_hash40(hash, dir_index));
+        self.fs_header.folder_count += 1;
+
+        if let Some(parent_index) = self.dir_info_index(parent) {
+            // Children are stored contiguously, so the parent's children have to be moved to the
+            // end of the table before the new directory can be added to them
+            let children = self.dir_infos[parent_index].children_range();
+            if children.end != self.folder_child_hashes.len() {
+                let moved = children.len() as u32;
+                self.dir_infos[parent_index].child_dir_start_index =
+                    self.folder_child_hashes.len() as u32;
+                self.folder_child_hashes.extend_from_within(children);
+                self.fs_header.hash_folder_count += moved;
+            }
+
+            self.folder_child_hashes
+                .push(HashToIndex::from_hash40(hash, dir_index));
+            self.fs_header.hash_folder_count += 1;
+            self.dir_infos[parent_index].child_dir_count += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Add a new non-regional file to an existing directory, returning the index of its
+    /// [`FilePath`]. `file_data` must point to data relative to the directory's folder offset.
+    pub fn add_file(
+        &mut self,
+        path: &str,
+        file_data: FileData,
+    ) -> Result<FilePathIdx, LookupError> {
+        let (parent, file_name) = split_path(path)?;
+        let hash = hash40(path);
+
+        let dir_index = self
+            .dir_info_index(hash40(parent))
+            .ok_or(LookupError::Missing)?;
+
+        let bucket_index = (hash.as_u64() % self.file_info_buckets.len() as u64) as usize;
+        let bucket = self.file_info_buckets[bucket_index];
+        let bucket_start = bucket.start as usize;
+        let bucket_end = bucket_start + bucket.count as usize;
+
+        let insert_at = match self.file_hash_to_path_index[bucket_start..bucket_end]
+            .binary_search_by_key(&hash, HashToIndex::hash40)
+        {
+            Ok(_) => return Err(LookupError::AlreadyExists),
+            Err(index) => bucket_start + index,
+        };
+
+        self.move_dir_files_to_end(dir_index);
+
+        let folder_offset_index = self.dir_infos[dir_index].path.index();
+        let file_path_index = self.file_paths.len() as u32;
+        let file_info_indice_index = self.file_info_indices.len() as u32;
+        let file_info_index = self.file_infos.len() as u32;
+        let info_to_data_index = self.file_info_to_datas.len() as u32;
+        let file_data_index = self.file_datas.len() as u32;
+
+        self.file_paths.push(FilePath {
+            path: HashToIndex::from_hash40(hash, file_info_indice_index),
+            ext: HashToIndex::from_hash40(hash40(extension(file_name)), 0),
+            parent: HashToIndex::from_hash40(hash40(parent), 0),
+            file_name: HashToIndex::from_hash40(hash40(file_name), 0),
+        });
+        self.file_info_indices.push(FileInfoIndex {
+            dir_offset_index: folder_offset_index,
+            file_info_index: FileInfoIdx(file_info_index),
+        });
+        self.file_infos.push(FileInfo {
+            file_path_index: FilePathIdx(file_path_index),
+            file_info_indice_index: FileInfoIndiceIdx(file_info_indice_index),
+            info_to_data_index: InfoToDataIdx(info_to_data_index),
+            flags: FileInfoFlags::new(),
+        });
+        self.file_info_to_datas.push(FileInfoToFileData {
+            folder_offset_index,
+            file_data_index: FileDataIdx(file_data_index),
+            file_info_index_and_load_type: FileInfoToFileDataBitfield::new()
+                .with_file_info_idx(file_info_index)
+                .with_load_type(1),
+        });
+        self.file_datas.push(file_data);
+
+        self.dir_infos[dir_index].file_count += 1;
+
+        self.file_hash_to_path_index
+            .insert(insert_at, HashToIndex::from_hash40(hash, file_path_index));
+        for (index, bucket) in self.file_info_buckets.iter_mut().enumerate() {
+            if index == bucket_index {
+                bucket.count += 1;
+            } else if bucket.start as usize >= insert_at {
+                bucket.start += 1;
+            }
+        }
+
+        let header = &mut self.fs_header;
+        header.file_info_path_count += 1;
+        header.file_info_index_count += 1;
+        header.extra_count += 1;
+        header.extra_count_2 += 1;
+        header.extra_sub_count += 1;
+
+        Ok(FilePathIdx(file_path_index))
+    }
+
+    /// The files of a directory are stored contiguously, so before a file can be added the
+    /// directory's existing [`FileInfo`]s are copied to the end of the table. The original
+    /// entries are left in place for anything else still referencing them.
+    fn move_dir_files_to_end(&mut self, dir_index: usize) {
+        let files = self.dir_infos[dir_index].file_info_range();
+        let new_start = self.file_infos.len();
+
+        if files.end == new_start {
+            return;
+        }
+
+        self.dir_infos[dir_index].file_info_start_index = new_start as u32;
+        if files.is_empty() {
+            return;
+        }
+
+        self.fs_header.extra_count += files.len() as u32;
+        self.file_infos.extend_from_within(files.clone());
+
+        for (old_index, new_index) in files.zip(new_start..) {
+            let file_info = self.file_infos[new_index];
+
+            let file_info_index =
+                &mut self.file_info_indices[usize::from(file_info.file_info_indice_index)];
+            if file_info_index.file_info_index == FileInfoIdx::from(old_index) {
+                file_info_index.file_info_index = FileInfoIdx::from(new_index);
+            }
+
+            // Regional files have one FileInfoToFileData per region, all pointing back to the
+            // same FileInfo
+            self.file_info_to_datas[usize::from(file_info.info_to_data_index)..]
+                .iter_mut()
+                .map(|info_to_data| &mut info_to_data.file_info_index_and_load_type)
+                .take_while(|bitfield| bitfield.file_info_idx() as usize == old_index)
+                .for_each(|bitfield| bitfield.set_file_info_idx(new_index as u32));
+        }
+    }
+}
+
+impl SearchFileSystem {
+    fn folder_index(&self, hash: Hash40) -> Option<usize> {
+        self.folder_lookup
+            .binary_search_by_key(&hash, HashToIndex::hash40)
+            .ok()
+            .map(|index| self.folder_lookup[index].index() as usize)
+    }
+
+    /// Add a new folder, along with any missing parent folders up to the top level, linking each
+    /// into its parent's list of children.
+    pub fn add_folder(&mut self, path: &str) -> Result<(), LookupError> {
+        let (parent_path, parent, name) = split_dir_path(path)?;
+        let hash = hash40(path);
+
+        if self.folder_index(hash).is_some() || self.contains_path(hash) {
+            return Err(LookupError::AlreadyExists);
+        }
+
+        if let Some(parent_path) = parent_path {
+            if self.folder_index(parent).is_none() {
+                self.add_folder(parent_path)?;
+            }
+        }
+
+        let folder = SearchListEntry {
+            path: HashToIndex::from_hash40(hash, 0xFF_FFFF),
+            parent: HashToIndex::from_hash40(parent, 0),
+            file_name: HashToIndex::from_hash40(hash40(name), 0),
+            ext: HashToIndex::new().with_hash(0xFFFF_FFFF),
+        };
+
+        let insert_at = self
+            .folder_lookup
+            .binary_search_by_key(&hash, HashToIndex::hash40)
+            .unwrap_err();
+        let folder_index = self.folders.len() as u32;
+
+        let folder = crate::FolderPathListEntry(folder);
+        let path_entry = folder.as_path_entry();
+
+        self.folders.push(folder);
+        self.folder_lookup
+            .insert(insert_at, HashToIndex::from_hash40(hash, folder_index));
+        self.header.folder_count += 1;
+
+        self.add_path_entry(path_entry, parent);
+
+        Ok(())
+    }
+
+    /// Add a new file, along with any missing parent folders
+    pub fn add_file(&mut self, path: &str) -> Result<(), LookupError> {
+        let (parent, file_name) = split_path(path)?;
+        let hash = hash40(path);
+
+        if self.contains_path(hash) {
+            return Err(LookupError::AlreadyExists);
+        }
+
+        if self.folder_index(hash40(parent)).is_none() {
+            self.add_folder(parent)?;
+        }
+
+        let entry = PathListEntry(SearchListEntry {
+            path: HashToIndex::from_hash40(hash, 0xFF_FFFF),
+            parent: HashToIndex::from_hash40(hash40(parent), 0),
+            file_name: HashToIndex::from_hash40(hash40(file_name), 0),
+            ext: HashToIndex::from_hash40(hash40(extension(file_name)), 0),
+        });
+
+        self.add_path_entry(entry, hash40(parent));
+
+        Ok(())
+    }
+
+    fn contains_path(&self, hash: Hash40) -> bool {
+        self.path_index_lookup
+            .binary_search_by_key(&hash, HashToIndex::hash40)
+            .is_ok()
+    }
+
+    /// Add an entry to the path list and make it the first child of its parent folder
+    fn add_path_entry(&mut self, mut entry: PathListEntry, parent: Hash40) {
+        let hash = entry.path.hash40();
+        let insert_at = self
+            .path_index_lookup
+            .binary_search_by_key(&hash, HashToIndex::hash40)
+            .unwrap_err();
+
+        let path_index = self.paths.len() as u32;
+        let path_indices_index = self.path_indices.len() as u32;
+
+        if let Some(folder_index) = self.folder_index(parent) {
+            let folder = &mut self.folders[folder_index];
+
+            entry.path.set_index(folder.get_first_child_index() as u32);
+            folder.set_first_child_index(path_indices_index);
+            let child_count = folder.get_child_count() as u32;
+            folder.parent.set_index(child_count + 1);
+        }
+
+        self.paths.push(entry);
+        self.path_indices.push(path_index);
+        self.path_index_lookup.insert(
+            insert_at,
+            HashToIndex::from_hash40(hash, path_indices_index),
+        );
+
+        self.header.path_count += 1;
+        self.header.path_index_count += 1;
+    }
+}
+
+impl ArcFile {
+    /// Add a new, empty directory, along with any missing parent directories. Files added to it
+    /// will be appended after the end of the data section.
+    pub fn add_dir(&mut self, path: &str) -> Result<(), LookupError> {
+        let append_start = self.data_end().max(self.patches.end());
+        let folder_start = (append_start + NEW_FOLDER_ALIGNMENT - 1) & !(NEW_FOLDER_ALIGNMENT - 1);
+
+        self.file_system
+            .add_dir(path, folder_start - self.file_section_offset)?;
+
+        match self.search_file_system.add_folder(path) {
+            Ok(()) | Err(LookupError::AlreadyExists) => (),
+            Err(err) => return Err(err),
+        }
+
+        #[cfg(feature = "dir-listing")]
+        self.add_to_dir_listing(path, true);
+
+        Ok(())
+    }
+
+    /// Add a new file to the arc, creating its parent directory if it doesn't exist.
+    ///
+    /// The data is appended after the end of the data section and kept in memory until the arc
+    /// is written out using [`ArcFile::write_to`].
+    pub fn add_file(
+        &mut self,
+        path: &str,
+        data: &[u8],
+        compression: Compression,
+    ) -> Result<(), LookupError> {
+        let (parent, _) = split_path(path)?;
+        let hash = hash40(path);
+
+        if self.get_file_path_index_from_hash(hash).is_ok()
+            || self.get_path_index_from_hash(hash).is_ok()
+        {
+            return Err(LookupError::AlreadyExists);
+        }
+
+        if self.get_dir_info_from_hash(parent).is_err() {
+            self.add_dir(parent)?;
+        }
+
+        let dir_info = *self.get_dir_info_from_hash(parent)?;
+        let folder_start = self.file_section_offset
+            + self.get_folder_offsets()[dir_info.path.index() as usize].offset;
+
+        let (stored, flags) = compression.encode(data, FileDataFlags::new())?;
+        let offset_in_folder = self.next_append_offset(folder_start)?;
+
+        let file_data = FileData {
+            offset_in_folder,
+            comp_size: u32::try_from(stored.len()).map_err(|_| too_large())?,
+            decomp_size: u32::try_from(data.len()).map_err(|_| too_large())?,
+            flags,
+        };
+
+        self.file_system.add_file(path, file_data)?;
+        self.search_file_system.add_file(path)?;
+        self.patches.insert(
+            folder_start + ((offset_in_folder as u64) << 2),
+            stored.into_owned(),
+        );
+
+        #[cfg(feature = "dir-listing")]
+        self.add_to_dir_listing(path, false);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arc_file::tests::{contents, rewrite, test_arc, TEST_FILE_CONTENTS};
+    use crate::FileInfoBucket;
+
+    fn folder_children(arc: &ArcFile, path: &str) -> Vec<Hash40> {
+        let mut children = Vec::new();
+        let mut child = arc.get_first_child_in_folder(path);
+        while let Ok(entry) = child {
+            children.push(entry.path.hash40());
+            child = arc.get_next_child_in_folder(entry);
+        }
+
+        children
+    }
+
+    fn dir_files(arc: &ArcFile, path: &str) -> Vec<Hash40> {
+        let dir_info = arc.get_dir_info_from_hash(path).unwrap();
+
+        arc.get_file_infos()[dir_info.file_info_range()]
+            .iter()
+            .map(|file_info| {
+                arc.get_file_paths()[file_info.file_path_index]
+                    .path
+                    .hash40()
+            })
+            .collect()
+    }
+
+    /// Spread the file hashes of the arc over multiple buckets
+    fn rebucket(arc: &mut ArcFile, count: u32) {
+        let fs = &mut arc.file_system;
+        let mut hashes = std::mem::take(&mut fs.file_hash_to_path_index);
+        hashes.sort_by_key(|hash| (hash.hash40().as_u64() % count as u64, hash.hash40()));
+
+        fs.file_info_buckets = (0..count as u64)
+            .map(|bucket| {
+                let start = hashes
+                    .iter()
+                    .take_while(|hash| hash.hash40().as_u64() % (count as u64) < bucket)
+                    .count();
+                let count = hashes[start..]
+                    .iter()
+                    .take_while(|hash| hash.hash40().as_u64() % count as u64 == bucket)
+                    .count();
+
+                FileInfoBucket {
+                    start: start as u32,
+                    count: count as u32,
+                }
+            })
+            .collect();
+        fs.file_hash_to_path_index = hashes;
+    }
+
+    #[test]
+    fn add_file_to_existing_dir() {
+        let mut arc = test_arc();
+        arc.add_file(
+            "fighter/mario/new.nutexb",
+            b"new file",
+            Compression::Uncompressed,
+        )
+        .unwrap();
+
+        for arc in [&arc, &rewrite(&arc)] {
+            assert_eq!(contents(arc, "fighter/mario/new.nutexb"), b"new file");
+            assert_eq!(
+                contents(arc, "fighter/mario/model.numdlb"),
+                TEST_FILE_CONTENTS
+            );
+            assert_eq!(
+                contents(arc, "fighter/mario/alias.numdlb"),
+                TEST_FILE_CONTENTS
+            );
+
+            let files = dir_files(arc, "fighter/mario");
+            assert_eq!(files.len(), 3);
+            assert!(files.contains(&hash40("fighter/mario/new.nutexb")));
+
+            let children = folder_children(arc, "fighter/mario");
+            assert_eq!(children.len(), 3);
+            assert_eq!(children[0], hash40("fighter/mario/new.nutexb"));
+            assert_eq!(
+                arc.get_folder_path_entry_from_hash("fighter/mario")
+                    .unwrap()
+                    .get_child_count(),
+                3
+            );
+
+            let entry = arc
+                .get_path_list_entry_from_hash("fighter/mario/new.nutexb")
+                .unwrap();
+            assert_eq!(entry.ext.hash40(), hash40("nutexb"));
+        }
+    }
+
+    #[test]
+    fn add_file_to_new_dir() {
+        let mut arc = test_arc();
+        arc.add_file(
+            "fighter/luigi/model.numdlb",
+            &[0x11; 0x400],
+            Compression::Zstd,
+        )
+        .unwrap();
+        arc.add_file(
+            "fighter/luigi/alias.numdlb",
+            b"second file",
+            Compression::Uncompressed,
+        )
+        .unwrap();
+
+        for arc in [&arc, &rewrite(&arc)] {
+            assert_eq!(contents(arc, "fighter/luigi/model.numdlb"), [0x11; 0x400]);
+            assert_eq!(contents(arc, "fighter/luigi/alias.numdlb"), b"second file");
+            assert_eq!(
+                contents(arc, "fighter/mario/model.numdlb"),
+                TEST_FILE_CONTENTS
+            );
+
+            let dir_info = arc.get_dir_info_from_hash("fighter/luigi").unwrap();
+            assert_eq!({ dir_info.parent }, hash40("fighter"));
+            assert_eq!({ dir_info.name }, hash40("luigi"));
+            assert_eq!(dir_files(arc, "fighter/luigi").len(), 2);
+
+            let folder = arc
+                .get_folder_path_entry_from_hash("fighter/luigi")
+                .unwrap();
+            assert_eq!(folder.parent.hash40(), hash40("fighter"));
+            assert_eq!(folder.get_child_count(), 2);
+            assert_eq!(folder_children(arc, "fighter/luigi").len(), 2);
+            assert!(arc
+                .get_path_list_entry_from_hash("fighter/luigi")
+                .unwrap()
+                .is_directory());
+        }
+    }
+
+    #[test]
+    fn add_child_dir() {
+        let mut arc = test_arc();
+        arc.add_dir("fighter/mario/c00").unwrap();
+        arc.add_dir("fighter/mario/c01").unwrap();
+
+        for arc in [&arc, &rewrite(&arc)] {
+            let dir_info = arc.get_dir_info_from_hash("fighter/mario").unwrap();
+            let children: Vec<_> = arc.file_system.folder_child_hashes[dir_info.children_range()]
+                .iter()
+                .map(|child| {
+                    arc.file_system.dir_infos[child.index() as usize]
+                        .path
+                        .hash40()
+                })
+                .collect();
+
+            assert_eq!(
+                children,
+                [hash40("fighter/mario/c00"), hash40("fighter/mario/c01")]
+            );
+        }
+    }
+
+    #[test]
+    fn add_nested_dir() {
+        let mut arc = test_arc();
+        arc.add_dir("fighter/luigi/model/body").unwrap();
+
+        for arc in [&arc, &rewrite(&arc)] {
+            for (path, parent) in [
+                ("fighter/luigi", "fighter"),
+                ("fighter/luigi/model", "fighter/luigi"),
+                ("fighter/luigi/model/body", "fighter/luigi/model"),
+            ] {
+                let dir_info = arc.get_dir_info_from_hash(path).unwrap();
+                assert_eq!({ dir_info.parent }, hash40(parent));
+
+                if let Ok(parent) = arc.get_dir_info_from_hash(parent) {
+                    let children = &arc.file_system.folder_child_hashes[parent.children_range()];
+                    assert_eq!(children.len(), 1);
+                    assert_eq!(children[0].hash40(), hash40(path));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn add_top_level_dir() {
+        let mut arc = test_arc();
+        arc.add_dir("stage/battlefield").unwrap();
+
+        for arc in [&arc, &rewrite(&arc)] {
+            let stage = arc.get_dir_info_from_hash("stage").unwrap();
+            assert_eq!({ stage.parent }, Hash40::from(0));
+            assert_eq!({ stage.name }, hash40("stage"));
+
+            let children = &arc.file_system.folder_child_hashes[stage.children_range()];
+            assert_eq!(children.len(), 1);
+            assert_eq!(children[0].hash40(), hash40("stage/battlefield"));
+
+            let folder = arc.get_folder_path_entry_from_hash("stage").unwrap();
+            assert_eq!(folder.parent.hash40(), Hash40::from(0));
+            assert_eq!(folder_children(arc, "stage"), [hash40("stage/battlefield")]);
+        }
+
+        assert!(matches!(arc.add_dir(""), Err(LookupError::InvalidPath)));
+    }
+
+    #[test]
+    fn add_file_table_sizes() {
+        use binrw::{io::Cursor, BinWrite, Endian};
+
+        fn table_len<T>(table: &T) -> u64
+        where
+            T: BinWrite,
+            for<'a> T::Args<'a>: Default,
+        {
+            let mut writer = Cursor::new(Vec::new());
+            table
+                .write_options(&mut writer, Endian::Little, Default::default())
+                .unwrap();
+
+            writer.into_inner().len() as u64
+        }
+
+        let original = test_arc();
+        let mut arc = test_arc();
+        arc.add_file(
+            "fighter/luigi/model.numdlb",
+            b"new file",
+            Compression::Uncompressed,
+        )
+        .unwrap();
+
+        let arc = rewrite(&arc);
+        let fs = &arc.file_system;
+        let search = &arc.search_file_system;
+
+        assert_eq!(fs.fs_header.table_filesize as u64, table_len(fs));
+        assert_eq!({ search.header.size }, table_len(search));
+        assert!(fs.fs_header.table_filesize > original.file_system.fs_header.table_filesize);
+        assert!({ search.header.size } > { original.search_file_system.header.size });
+    }
+
+    #[test]
+    fn add_many_files() {
+        let mut arc = test_arc();
+        rebucket(&mut arc, 7);
+
+        let paths: Vec<_> = (0..64)
+            .map(|index| format!("fighter/mario/motion/{:02}.nuanmb", index))
+            .collect();
+        for path in &paths {
+            arc.add_file(path, path.as_bytes(), Compression::Uncompressed)
+                .unwrap();
+        }
+
+        for arc in [&arc, &rewrite(&arc)] {
+            for path in &paths {
+                assert_eq!(contents(arc, path), path.as_bytes());
+            }
+            assert_eq!(
+                contents(arc, "fighter/mario/model.numdlb"),
+                TEST_FILE_CONTENTS
+            );
+            assert_eq!(folder_children(arc, "fighter/mario/motion").len(), 64);
+        }
+    }
+
+    #[test]
+    fn add_existing_file() {
+        let mut arc = test_arc();
+
+        assert!(matches!(
+            arc.add_file("fighter/mario/model.numdlb", b"", Compression::Uncompressed),
+            Err(LookupError::AlreadyExists)
+        ));
+        assert!(matches!(
+            arc.add_file("model.numdlb", b"", Compression::Uncompressed),
+            Err(LookupError::InvalidPath)
+        ));
+        assert!(matches!(
+            arc.add_dir("fighter/mario"),
+            Err(LookupError::AlreadyExists)
+        ));
+    }
+
+    #[cfg(feature = "dir-listing")]
+    #[test]
+    fn add_file_dir_listing() {
+        let mut arc = test_arc();
+        arc.add_file("fighter/koopa/model.numdlb", b"", Compression::Uncompressed)
+            .unwrap();
+
+        assert!(arc
+            .get_dir_listing("fighter/koopa")
+            .unwrap()
+            .contains(&crate::FileNode::File(hash40("fighter/koopa/model.numdlb"))));
+        assert!(arc
+            .get_dir_listing("fighter")
+            .unwrap()
+            .contains(&crate::FileNode::Dir(hash40("fighter/koopa"))));
+    }
+}
@@ -1,21 +1,47 @@
 use std::{
-    collections::{HashMap, HashSet},
     fs::File,
-    io::{BufReader, Seek, SeekFrom},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
     net::ToSocketAddrs,
     path::Path,
-    sync::Mutex,
+    sync::{Arc, Mutex},
+};
+
+#[cfg(feature = "dir-listing")]
+use std::{
+    collections::{HashMap, HashSet},
+    sync::OnceLock,
 };
 
-use binrw::{io::Cursor, BinReaderExt, BinResult, FilePtr64, binread};
+use parking_lot::RwLock;
+
+use binrw::{binread, io::Cursor, BinReaderExt, BinResult, BinWrite, BinWriterExt, FilePtr64};
 
+#[cfg(feature = "dir-listing")]
 use crate::filesystem::HashToIndex;
 use crate::hash_labels::HashLabels;
+#[cfg(feature = "global-hashes")]
+use crate::hash_labels::GLOBAL_LABELS;
+use crate::read_at::ReadAtCursor;
+use crate::replace::DataPatches;
 use crate::{
-    CompressedFileSystem, CompressedSearchFileSystem, FileNode, FileSystem, Hash40,
-    SearchFileSystem,
+    ArcLookup, CompressedFileSystem, CompressedSearchFileSystem, FileNode, FileSystem, Hash40,
+    ReadAt, SearchFileSystem,
 };
 
+/// The memory map used by [`ArcFile::open_mmap`]. binrw doesn't support conditionally compiled
+/// fields, so without the `mmap` feature this is a type that can never be constructed.
+#[cfg(feature = "mmap")]
+pub(crate) type ArcMmap = std::sync::Arc<memmap2::Mmap>;
+#[cfg(not(feature = "mmap"))]
+pub(crate) type ArcMmap = std::convert::Infallible;
+
+/// Where the directory listing is cached, see [`ArcMmap`] for why this isn't conditionally
+/// compiled. Without the `dir-listing` feature nothing is cached.
+#[cfg(feature = "dir-listing")]
+pub(crate) type DirListingCell = OnceLock<DirListing>;
+#[cfg(not(feature = "dir-listing"))]
+pub(crate) type DirListingCell = ();
+
 pub trait SeekRead: std::io::Read + std::io::Seek {}
 impl<R: std::io::Read + std::io::Seek> SeekRead for R {}
 
@@ -26,13 +52,21 @@ pub struct ArcFile {
     pub stream_section_offset: u64,
     pub file_section_offset: u64,
     pub shared_section_offset: u64,
-    
+
+    /// Offset of the compressed [`FileSystem`] table, which also marks the end of file data
+    #[br(restore_position)]
+    pub file_system_offset: u64,
+
     #[br(temp, parse_with = FilePtr64::parse)]
     compressed_file_system: CompressedFileSystem,
 
     #[br(calc = compressed_file_system.0)]
     pub file_system: FileSystem,
 
+    /// Offset of the compressed [`SearchFileSystem`] table
+    #[br(restore_position)]
+    pub search_file_system_offset: u64,
+
     #[br(temp, parse_with = FilePtr64::parse)]
     compressed_search_file_system: CompressedSearchFileSystem,
 
@@ -42,130 +76,230 @@ pub struct ArcFile {
     #[br(calc = Mutex::new(Box::new(Cursor::new([])) as _))]
     pub reader: Mutex<Box<dyn SeekRead + Send>>,
 
-    #[cfg(feature = "dir-listing")]
-    #[br(calc = generate_dir_listing(&file_system))]
-    pub dirs: HashMap<Hash40, Vec<FileNode>>,
+    /// The reader used for positional reads when opened using [`ArcFile::from_read_at`]
+    #[br(calc = None)]
+    pub(crate) reader_at: Option<Arc<dyn ReadAt + Send + Sync>>,
+
+    #[br(calc = Default::default())]
+    pub(crate) patches: DataPatches,
+
+    #[br(calc = None)]
+    #[cfg_attr(not(feature = "mmap"), allow(dead_code))]
+    pub(crate) mmap: Option<ArcMmap>,
+
+    /// The labels used by this arc, or `None` to use the global labels
+    #[br(calc = None)]
+    pub(crate) labels: Option<Arc<RwLock<HashLabels>>>,
+
+    /// The listing of every directory, built on the first call to [`ArcFile::get_dir_listing`]
+    /// and rebuilt whenever the global labels it was built from are replaced
+    #[br(calc = Default::default())]
+    #[cfg_attr(not(feature = "dir-listing"), allow(dead_code))]
+    pub(crate) dirs: DirListingCell,
 }
 
-#[cfg(feature = "dir-listing")]
-fn parents_of_dir(dir: Hash40, labels: &mut HashLabels) -> Option<Vec<(Hash40, FileNode)>> {
-    let label = dir.label(labels)?.to_owned();
-    let mut label = &label[..];
-    let mut hashes = Vec::new();
-    let mut last_hash = dir;
+/// The header of the data.arc as written by [`ArcFile::write_to`]
+#[derive(BinWrite)]
+#[bw(magic = 0xABCD_EF98_7654_3210_u64)]
+struct ArcHeader {
+    stream_section_offset: u64,
+    file_section_offset: u64,
+    shared_section_offset: u64,
+    file_system_offset: u64,
+    search_file_system_offset: u64,
+    padding: u64,
+}
+
+pub(crate) const ARC_HEADER_SIZE: u64 = 0x38;
+const TABLE_ALIGNMENT: u64 = 0x10;
 
-    while let Some(len) = label.trim_end_matches('/').rfind('/') {
-        label = &label[..len];
+/// The listing of every directory
+#[cfg(feature = "dir-listing")]
+pub(crate) struct DirListing {
+    pub(crate) dirs: HashMap<Hash40, Vec<FileNode>>,
+    /// Names of directories which are implied by the layout of the tables rather than labeled,
+    /// such as the parents of stream directories. Kept here rather than added to any labels.
+    pub(crate) names: HashMap<Hash40, String>,
+}
 
-        let hash = labels.add_label(label);
-        hashes.push((hash, FileNode::Dir(last_hash)));
+/// Link a directory to each of its parents up to the root, naming each parent along the way
+#[cfg(feature = "dir-listing")]
+fn link_parents(
+    mut path: &str,
+    pairs: &mut Vec<(Hash40, FileNode)>,
+    names: &mut HashMap<Hash40, String>,
+) {
+    let mut last_hash = crate::hash40::hash40(path);
+
+    while let Some(len) = path.trim_end_matches('/').rfind('/') {
+        path = &path[..len];
+
+        let hash = crate::hash40::hash40(path);
+        pairs.push((hash, FileNode::Dir(last_hash)));
+        names.entry(hash).or_insert_with(|| path.to_owned());
         last_hash = hash;
     }
 
-    hashes.push((crate::hash40::hash40("/"), FileNode::Dir(last_hash)));
+    pairs.push((crate::hash40::hash40("/"), FileNode::Dir(last_hash)));
+}
+
+/// The parent of the directory listing the stream files of a [`QuickDir`](crate::QuickDir), which
+/// only stores the name of the directory. These are within `stream:/sound`, except for
+/// `stream:/movie` (the same layout as [`ArcLookup::get_stream_listing`]).
+#[cfg(feature = "dir-listing")]
+fn quick_dir_parent(name: Hash40) -> &'static str {
+    if name == crate::hash40::hash40("movie") {
+        "stream:"
+    } else {
+        "stream:/sound"
+    }
+}
+
+/// Parent and child pairs of stream files, taken from the [`QuickDir`](crate::QuickDir) each is
+/// listed in. Any stream file which isn't in a quick dir is listed directly in `stream:`.
+#[cfg(feature = "dir-listing")]
+fn stream_dir_listing(
+    fs: &FileSystem,
+    names: &mut HashMap<Hash40, String>,
+) -> Vec<(Hash40, FileNode)> {
+    let mut pairs = Vec::new();
+    let mut listed = HashSet::new();
+
+    for quick_dir in &fs.quick_dirs {
+        let parent = quick_dir_parent(quick_dir.hash40());
+        let dir = crate::hash40::hash40(parent)
+            .concat(crate::hash40::hash40("/"))
+            .concat(quick_dir.hash40());
+
+        pairs.push((crate::hash40::hash40(parent), FileNode::Dir(dir)));
+        link_parents(parent, &mut pairs, names);
+        names
+            .entry(crate::hash40::hash40(parent))
+            .or_insert_with(|| parent.to_owned());
+
+        let start = quick_dir.index() as usize;
+        let entries = fs
+            .stream_entries
+            .get(start..start + quick_dir.count() as usize)
+            .unwrap_or_default();
+        for path_hash in entries.iter().map(|entry| entry.path.hash40()) {
+            pairs.push((dir, FileNode::File(path_hash)));
+            listed.insert(path_hash);
+        }
+    }
+
+    let stream = crate::hash40::hash40("stream:");
+    for path_hash in fs.stream_hash_to_entries.iter().map(HashToIndex::hash40) {
+        if listed.insert(path_hash) {
+            pairs.push((stream, FileNode::File(path_hash)));
+            names.entry(stream).or_insert_with(|| "stream:".to_owned());
+        }
+    }
 
-    Some(hashes)
+    pairs
 }
 
+/// Parent and child pairs taken from the structure of the tables themselves: the parents of each
+/// file path and [`DirInfo`](crate::DirInfo), and of each folder and path in the
+/// [`SearchFileSystem`]
 #[cfg(feature = "dir-listing")]
-fn dir_listing_flat<'a>(
+fn structural_dir_listing<'a>(
     fs: &'a FileSystem,
-    labels: &'a mut HashLabels,
+    search: &'a SearchFileSystem,
 ) -> impl Iterator<Item = (Hash40, FileNode)> + 'a {
-    let dirs: HashSet<_> = fs
+    let file_paths = fs
         .file_paths
         .iter()
-        .map(|path| path.parent.hash40())
-        .collect();
-
-    let mut stream_dirs = Vec::new();
-    for path_hash in fs.stream_hash_to_entries.iter().map(HashToIndex::hash40) {
-        if let Some(label) = path_hash
-            .label(labels)
-            .and_then(|label| label.rfind('/').map(|pos| label[..pos].to_owned()))
-        {
-            let mut label = &label[..];
-            let mut last_hash = crate::hash40::hash40(label);
-            labels.add_label(label);
-
-            while let Some(len) = label.trim_end_matches('/').rfind('/') {
-                label = &label[..len];
+        .map(|path| (path.parent.hash40(), FileNode::File(path.path.hash40())));
 
-                let hash = labels.add_label(label);
-                stream_dirs.push((hash, FileNode::Dir(last_hash)));
-                last_hash = hash;
-            }
+    let dir_infos = fs
+        .dir_infos
+        .iter()
+        .map(|dir| (dir.parent, FileNode::Dir(dir.path.hash40())));
 
-            stream_dirs.push((crate::hash40::hash40("/"), FileNode::Dir(last_hash)));
+    let folders = search
+        .folders
+        .iter()
+        .map(|folder| (folder.parent.hash40(), FileNode::Dir(folder.path.hash40())));
+
+    let paths = search.paths.iter().map(|path| {
+        let node = if path.is_directory() {
+            FileNode::Dir(path.path.hash40())
+        } else {
+            FileNode::File(path.path.hash40())
+        };
+
+        (path.parent.hash40(), node)
+    });
+
+    file_paths
+        .chain(dir_infos)
+        .chain(folders)
+        .chain(paths)
+        // Top level directories may have an empty parent
+        .map(|(parent, child)| match parent.as_u64() {
+            0 => (crate::hash40::hash40("/"), child),
+            _ => (parent, child),
+        })
+}
 
-            labels.add_label(label);
-        }
+/// Build the listing of every directory from the structure of the tables alone, so it doesn't
+/// depend on which labels are loaded. Any directory which is left without a parent is listed in
+/// the root.
+#[cfg(feature = "dir-listing")]
+fn build_dir_listing(
+    fs: &FileSystem,
+    search: &SearchFileSystem,
+) -> (HashMap<Hash40, Vec<FileNode>>, HashMap<Hash40, String>) {
+    let mut names = HashMap::new();
+
+    // Sorting every pair at once rather than inserting each into a sorted listing keeps large
+    // directories from taking quadratic time
+    let mut pairs = stream_dir_listing(fs, &mut names);
+    pairs.extend(structural_dir_listing(fs, search));
+    pairs.sort_unstable();
+    pairs.dedup();
+
+    let mut dirs: HashMap<Hash40, Vec<FileNode>> = HashMap::new();
+    for (parent, child) in pairs {
+        dirs.entry(parent).or_default().push(child);
     }
 
-    let stream_paths: Vec<(Hash40, &str)> = fs
-        .stream_hash_to_entries
-        .iter()
-        .filter_map(|entry| {
-            entry
-                .hash40()
-                .label(labels)
-                .map(|path| (entry.hash40(), path))
+    let root = crate::hash40::hash40("/");
+    let children: HashSet<Hash40> = dirs
+        .values()
+        .flatten()
+        .filter_map(|node| match node {
+            FileNode::Dir(dir) => Some(*dir),
+            FileNode::File(_) => None,
         })
         .collect();
-    let stream_files: Vec<(Hash40, FileNode)> = stream_paths
-        .iter()
-        .flat_map(|(path_hash, path)| {
-            path.rfind('/').map(|pos| {
-                let dir = crate::hash40::hash40(&path[..pos]);
-
-                (dir, FileNode::File(*path_hash))
-            })
-        })
+    let mut orphans: Vec<FileNode> = dirs
+        .keys()
+        .filter(|dir| **dir != root && !children.contains(dir))
+        .map(|dir| FileNode::Dir(*dir))
         .collect();
 
-    // Generate parents for directories
-    let dirs = dirs
-        .into_iter()
-        .filter_map(move |dir| parents_of_dir(dir, labels).map(|x| x.into_iter()))
-        .flatten();
-
-    // Generate parents for files
-    fs.file_paths
-        .iter()
-        .map(|path| (path.parent.hash40(), FileNode::File(path.path.hash40())))
-        .chain(dirs)
-        .chain(stream_files.into_iter())
-        .chain(stream_dirs.into_iter())
-}
-
-#[cfg(feature = "dir-listing")]
-fn generate_dir_listing(fs: &FileSystem) -> HashMap<Hash40, Vec<FileNode>> {
-    let mut dirs = HashMap::new();
-
-    let mut labels = crate::hash_labels::GLOBAL_LABELS.write();
-    for (parent, child) in dir_listing_flat(fs, &mut labels) {
-        let listing = dirs.entry(parent).or_insert_with(Vec::new);
-        match listing.binary_search(&child) {
-            Ok(_) => (),
-            Err(insert_point) => listing.insert(insert_point, child),
-        }
+    if !orphans.is_empty() {
+        let listing = dirs.entry(root).or_default();
+        listing.append(&mut orphans);
+        listing.sort();
+        listing.dedup();
     }
 
-    dirs
+    (dirs, names)
 }
 
 impl ArcFile {
     pub fn open<P: AsRef<Path>>(path: P) -> BinResult<Self> {
-        Self::from_reader(BufReader::new(File::open(path)?))
+        Self::from_read_at(crate::read_at::open_read_at(path)?)
     }
 
     #[cfg(feature = "network")]
     pub fn open_over_network<Addr: ToSocketAddrs>(ip: Addr) -> BinResult<Self> {
-        let mut reader = BufReader::new(network_reader::NetworkReader::new(ip)?);
-
-        reader.seek(SeekFrom::Start(0))?;
+        let reader = crate::network_reader::NetworkReader::new(ip)?;
 
-        Self::from_reader(reader)
+        Self::from_read_at(Mutex::new(reader))
     }
 
     pub fn from_reader<R: SeekRead + Send + 'static>(mut reader: R) -> BinResult<Self> {
@@ -176,15 +310,492 @@ impl ArcFile {
         Ok(arc)
     }
 
+    /// Parse an arc from a reader supporting positional reads. File contents are then read
+    /// without locking [`ArcFile::reader`], so they can be read from multiple threads at once.
+    pub fn from_read_at<R: ReadAt + Send + Sync + 'static>(reader: R) -> BinResult<Self> {
+        let reader: Arc<dyn ReadAt + Send + Sync> = Arc::new(reader);
+
+        let mut cursor = BufReader::new(ReadAtCursor::new(Arc::clone(&reader)));
+        let mut arc: Self = cursor.read_le()?;
+
+        *arc.reader.get_mut().unwrap() = Box::new(cursor);
+        arc.reader_at = Some(reader);
+
+        Ok(arc)
+    }
+
+    /// Open an arc which uses its own labels rather than the global labels, such as for working
+    /// with arcs from multiple game versions at once. The labels may be shared between arcs.
+    pub fn open_with_labels<P: AsRef<Path>>(
+        path: P,
+        labels: Arc<RwLock<HashLabels>>,
+    ) -> BinResult<Self> {
+        let mut arc = Self::open(path)?;
+        arc.set_labels(Some(labels));
+
+        Ok(arc)
+    }
+
+    /// Set the labels used by this arc, or `None` to use the global labels (if the
+    /// `global-hashes` feature is enabled)
+    pub fn set_labels(&mut self, labels: Option<Arc<RwLock<HashLabels>>>) {
+        self.labels = labels;
+    }
+
+    /// The labels used by this arc for naming files. These are [`GLOBAL_LABELS`] unless set using
+    /// [`ArcFile::open_with_labels`] or [`ArcFile::set_labels`].
+    #[cfg(feature = "global-hashes")]
+    pub fn labels(&self) -> &RwLock<HashLabels> {
+        self.labels.as_deref().unwrap_or(&GLOBAL_LABELS)
+    }
+
+    /// The label of a hash using this arc's labels. Without labels set using
+    /// [`ArcFile::set_labels`], the global labels are used if the `global-hashes` feature is
+    /// enabled. Directories named by the directory listing (such as `stream:/sound`) are used for
+    /// any hash which isn't labeled, once the listing has been built.
+    pub fn label<Hash: Into<Hash40>>(&self, hash: Hash) -> Option<String> {
+        let hash = hash.into();
+
+        let label = match &self.labels {
+            Some(labels) => hash.label(&labels.read()).map(str::to_owned),
+            #[cfg(feature = "global-hashes")]
+            None => hash.global_label(),
+            #[cfg(not(feature = "global-hashes"))]
+            None => None,
+        };
+
+        #[cfg(feature = "dir-listing")]
+        let label = label.or_else(|| self.dirs.get()?.names.get(&hash).cloned());
+
+        label
+    }
+
+    /// Write a complete data.arc to the given path. See [`ArcFile::write_to`].
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> BinResult<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Write a complete data.arc, using the current state of [`ArcFile::file_system`] and
+    /// [`ArcFile::search_file_system`].
+    ///
+    /// The stream, file and shared data sections are copied as-is from the reader the arc was
+    /// opened from, so the offsets stored in the tables remain valid, followed by any data added
+    /// using [`ArcFile::replace_file`]. Both tables are then recompressed and appended after the
+    /// data.
+    ///
+    /// **Note:** The writer must not point to the file this arc is being read from.
+    pub fn write_to<W: Write + Seek>(&self, writer: &mut W) -> BinResult<()> {
+        let start = writer.stream_position()?;
+        writer.write_all(&[0; ARC_HEADER_SIZE as usize])?;
+
+        let data_end = self.data_end().max(self.patches.end());
+        {
+            let mut reader = self.get_file_reader();
+            reader.seek(SeekFrom::Start(ARC_HEADER_SIZE))?;
+
+            let data_size = data_end - ARC_HEADER_SIZE;
+            if io::copy(&mut reader.take(data_size), writer)? != data_size {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "the arc data section ended early",
+                )
+                .into());
+            }
+        }
+
+        let file_system_offset = align_writer(writer, start)?;
+        self.file_system.write_compressed(writer)?;
+
+        let search_file_system_offset = align_writer(writer, start)?;
+        self.search_file_system.write_compressed(writer)?;
+
+        let end = writer.stream_position()?;
+
+        writer.seek(SeekFrom::Start(start))?;
+        writer.write_le(&ArcHeader {
+            stream_section_offset: self.stream_section_offset,
+            file_section_offset: self.file_section_offset,
+            shared_section_offset: self.shared_section_offset,
+            file_system_offset,
+            search_file_system_offset,
+            padding: 0,
+        })?;
+        writer.seek(SeekFrom::Start(end))?;
+
+        Ok(())
+    }
+
+    /// Get the files and directories within a directory, sorted by hash.
+    ///
+    /// The listing of every directory is built from the tables of the arc the first time this is
+    /// called, so it doesn't depend on labels, which are only needed to name the files (see
+    /// [`ArcFile::label`]). Replacing the labels afterwards doesn't affect the listing, which is
+    /// only rebuilt after [`ArcFile::invalidate_dir_listing`].
     #[cfg(feature = "dir-listing")]
     pub fn get_dir_listing<Hash: Into<Hash40>>(&self, hash: Hash) -> Option<&[FileNode]> {
-        self.dirs.get(&hash.into()).map(AsRef::as_ref)
+        self.dir_listing().get(&hash.into()).map(AsRef::as_ref)
+    }
+
+    /// The listing of every directory, mapping each directory to its files and directories sorted
+    /// by hash. Built the same way as for [`ArcFile::get_dir_listing`].
+    #[cfg(feature = "dir-listing")]
+    pub fn dir_listing(&self) -> &HashMap<Hash40, Vec<FileNode>> {
+        &self
+            .dirs
+            .get_or_init(|| {
+                let (dirs, names) = build_dir_listing(&self.file_system, &self.search_file_system);
+                DirListing { dirs, names }
+            })
+            .dirs
+    }
+
+    /// Discard the directory listing, so it is rebuilt from the tables on the next call to
+    /// [`ArcFile::get_dir_listing`]
+    #[cfg(feature = "dir-listing")]
+    pub fn invalidate_dir_listing(&mut self) {
+        self.dirs = OnceLock::new();
+    }
+
+    /// Insert a newly added file or directory into the directory listing, along with any of its
+    /// parents which aren't listed yet. If the listing hasn't been built yet there is nothing to
+    /// do, as it is built from the tables the path was added to.
+    #[cfg(feature = "dir-listing")]
+    pub(crate) fn add_to_dir_listing(&mut self, path: &str, is_dir: bool) {
+        let listing = match self.dirs.get_mut() {
+            Some(listing) => listing,
+            None => return,
+        };
+
+        let hash = crate::hash40::hash40(path);
+        let node = if is_dir {
+            FileNode::Dir(hash)
+        } else {
+            FileNode::File(hash)
+        };
+
+        let mut pairs = Vec::new();
+        match path.rfind('/') {
+            Some(pos) => {
+                let parent = &path[..pos];
+                pairs.push((crate::hash40::hash40(parent), node));
+                link_parents(parent, &mut pairs, &mut listing.names);
+                listing
+                    .names
+                    .entry(crate::hash40::hash40(parent))
+                    .or_insert_with(|| parent.to_owned());
+            }
+            None => pairs.push((crate::hash40::hash40("/"), node)),
+        }
+
+        let dirs = &mut listing.dirs;
+        for (parent, child) in pairs {
+            let listing = dirs.entry(parent).or_default();
+            if let Err(insert_point) = listing.binary_search(&child) {
+                listing.insert(insert_point, child);
+            }
+        }
     }
 }
 
+/// Pad the writer with zeroes until it is aligned relative to `start`, returning the aligned
+/// offset relative to `start`
+fn align_writer<W: Write + Seek>(writer: &mut W, start: u64) -> io::Result<u64> {
+    let offset = writer.stream_position()? - start;
+    let aligned = (offset + TABLE_ALIGNMENT - 1) & !(TABLE_ALIGNMENT - 1);
+    writer.write_all(&vec![0; (aligned - offset) as usize])?;
+
+    Ok(aligned)
+}
+
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
+    use crate::filesystem::tests::{test_file_system, test_search_file_system};
+    use crate::{ArcLookup, Region};
+
+    pub(crate) const TEST_FILE_CONTENTS: &[u8; 0x10] = b"mario model data";
+    pub(crate) const TEST_STREAM_CONTENTS: &[u8; 0x10] = b"bgm stream data!";
+
+    /// Build the bytes of a minimal data.arc around [`test_file_system`], with the stream section
+    /// at 0x100, the file section at 0x200 and the tables following the (empty) shared section.
+    pub(crate) fn test_arc_bytes() -> Vec<u8> {
+        let mut writer = Cursor::new(vec![0; 0x300]);
+
+        writer.set_position(0x100);
+        writer.write_all(TEST_STREAM_CONTENTS).unwrap();
+        writer.set_position(0x200);
+        writer.write_all(TEST_FILE_CONTENTS).unwrap();
+
+        writer.set_position(0x300);
+        test_file_system().write_compressed(&mut writer).unwrap();
+        let search_file_system_offset = align_writer(&mut writer, 0).unwrap();
+        test_search_file_system()
+            .write_compressed(&mut writer)
+            .unwrap();
+
+        writer.set_position(0);
+        writer
+            .write_le(&ArcHeader {
+                stream_section_offset: 0x100,
+                file_section_offset: 0x200,
+                shared_section_offset: 0x300,
+                file_system_offset: 0x300,
+                search_file_system_offset,
+                padding: 0,
+            })
+            .unwrap();
+
+        writer.into_inner()
+    }
+
+    pub(crate) fn test_arc() -> ArcFile {
+        ArcFile::from_reader(Cursor::new(test_arc_bytes())).unwrap()
+    }
+
+    /// Write the arc out and read it back in
+    pub(crate) fn rewrite(arc: &ArcFile) -> ArcFile {
+        let mut writer = Cursor::new(Vec::new());
+        arc.write_to(&mut writer).unwrap();
+
+        ArcFile::from_reader(Cursor::new(writer.into_inner())).unwrap()
+    }
+
+    pub(crate) fn contents(arc: &ArcFile, path: &str) -> Vec<u8> {
+        arc.get_file_contents(path, Region::UsEnglish).unwrap()
+    }
+
+    #[test]
+    fn write_unmodified_arc() {
+        let bytes = test_arc_bytes();
+        let arc = test_arc();
+
+        let mut writer = Cursor::new(Vec::new());
+        arc.write_to(&mut writer).unwrap();
+
+        assert_eq!(writer.into_inner(), bytes);
+    }
+
+    #[test]
+    fn write_modified_tables() {
+        let mut arc = test_arc();
+        arc.file_system.stream_datas[0].size = 4;
+
+        let mut writer = Cursor::new(Vec::new());
+        arc.write_to(&mut writer).unwrap();
+
+        let arc = ArcFile::from_reader(Cursor::new(writer.into_inner())).unwrap();
+
+        assert_eq!(
+            arc.get_file_contents("fighter/mario/model.numdlb", Region::UsEnglish)
+                .unwrap(),
+            TEST_FILE_CONTENTS
+        );
+        assert_eq!(
+            arc.get_file_contents("stream:/sound/bgm/bgm_test.nus3audio", Region::UsEnglish)
+                .unwrap(),
+            &TEST_STREAM_CONTENTS[..4]
+        );
+    }
+
+    #[cfg(feature = "dir-listing")]
+    #[test]
+    fn dir_listing_without_labels() {
+        use crate::hash40::hash40;
+
+        let (dirs, names) = build_dir_listing(&test_file_system(), &test_search_file_system());
+
+        let mut root = vec![
+            FileNode::Dir(hash40("fighter")),
+            FileNode::Dir(hash40("stream:")),
+        ];
+        root.sort();
+        assert_eq!(dirs[&hash40("/")], root);
+        assert_eq!(
+            dirs[&hash40("fighter")],
+            [FileNode::Dir(hash40("fighter/mario"))]
+        );
+
+        let mut files = vec![
+            FileNode::File(hash40("fighter/mario/model.numdlb")),
+            FileNode::File(hash40("fighter/mario/alias.numdlb")),
+        ];
+        files.sort();
+        assert_eq!(dirs[&hash40("fighter/mario")], files);
+
+        // Stream files are listed using the quick dirs of the stream tables
+        assert_eq!(
+            dirs[&hash40("stream:")],
+            [FileNode::Dir(hash40("stream:/sound"))]
+        );
+        assert_eq!(
+            dirs[&hash40("stream:/sound")],
+            [FileNode::Dir(hash40("stream:/sound/bgm"))]
+        );
+        assert_eq!(
+            dirs[&hash40("stream:/sound/bgm")],
+            [FileNode::File(hash40(
+                "stream:/sound/bgm/bgm_test.nus3audio"
+            ))]
+        );
+
+        let mut names: Vec<_> = names.into_values().collect();
+        names.sort_unstable();
+        assert_eq!(names, ["stream:", "stream:/sound"]);
+    }
+
+    #[cfg(feature = "dir-listing")]
+    #[test]
+    fn lazy_dir_listing() {
+        use crate::hash40::hash40;
+
+        const STREAM: &str = "stream:/sound/bgm/bgm_test.nus3audio";
+
+        let mut arc = test_arc();
+        assert!(arc.dirs.get().is_none());
+        assert!(arc.get_dir_listing("fighter/mario").is_some());
+        assert!(arc.dirs.get().is_some());
+        assert_eq!(
+            arc.dir_listing()[&hash40("fighter/mario")],
+            arc.get_dir_listing("fighter/mario").unwrap()
+        );
+
+        arc.invalidate_dir_listing();
+        assert!(arc.dirs.get().is_none());
+        assert_eq!(
+            arc.get_dir_listing("stream:/sound/bgm").unwrap(),
+            [FileNode::File(hash40(STREAM))]
+        );
+    }
+
+    #[cfg(feature = "dir-listing")]
+    #[test]
+    fn dir_listing_ignores_labels() {
+        use crate::hash40::hash40;
+
+        let mut arc = test_arc();
+        let listing: *const _ = arc.dir_listing();
+
+        // Replacing the labels keeps the listing, as it doesn't depend on them
+        arc.set_labels(Some(Arc::new(RwLock::new(HashLabels::new()))));
+        assert!(std::ptr::eq(listing, arc.dir_listing()));
+
+        arc.add_to_dir_listing("fighter/luigi/model", true);
+        assert!(arc
+            .get_dir_listing("fighter")
+            .unwrap()
+            .contains(&FileNode::Dir(hash40("fighter/luigi"))));
+        assert_eq!(
+            arc.get_dir_listing("fighter/luigi").unwrap(),
+            [FileNode::Dir(hash40("fighter/luigi/model"))]
+        );
+        assert_eq!(arc.label("fighter/luigi").as_deref(), Some("fighter/luigi"));
+        assert!(arc.labels().read().labels.is_empty());
+    }
+
+    #[cfg(feature = "dir-listing")]
+    #[test]
+    fn per_arc_labels() {
+        use crate::hash40::hash40;
+
+        const STREAM: &str = "stream:/sound/bgm/bgm_test.nus3audio";
+
+        let mut labeled = HashLabels::new();
+        labeled.add_label(STREAM);
+
+        let mut arc = test_arc();
+        arc.set_labels(Some(Arc::new(RwLock::new(labeled))));
+        let mut unlabeled_arc = test_arc();
+        unlabeled_arc.set_labels(Some(Arc::new(RwLock::new(HashLabels::new()))));
+
+        assert_eq!(
+            arc.get_dir_listing("stream:/sound/bgm").unwrap(),
+            [FileNode::File(hash40(STREAM))]
+        );
+        assert_eq!(arc.label("stream:/sound").as_deref(), Some("stream:/sound"));
+
+        // The listing doesn't depend on labels, only the names do
+        assert_eq!(
+            unlabeled_arc.get_dir_listing("stream:/sound/bgm").unwrap(),
+            [FileNode::File(hash40(STREAM))]
+        );
+        assert_eq!(unlabeled_arc.label(STREAM), None);
+        assert_eq!(unlabeled_arc.label("stream:/sound/bgm"), None);
+        assert_eq!(
+            unlabeled_arc.label("stream:/sound").as_deref(),
+            Some("stream:/sound")
+        );
+
+        // Names found while building the listing are kept out of the labels
+        assert!(unlabeled_arc.labels().read().labels.is_empty());
+        assert_eq!(arc.labels().read().labels.len(), 1);
+    }
+
+    #[test]
+    fn read_at_without_locking() {
+        let arc = ArcFile::from_read_at(test_arc_bytes()).unwrap();
+
+        // Holding the reader's lock proves reads don't go through it
+        let _lock = arc.reader.lock().unwrap();
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    assert_eq!(
+                        arc.get_file_contents("fighter/mario/model.numdlb", Region::UsEnglish)
+                            .unwrap(),
+                        TEST_FILE_CONTENTS
+                    );
+                    assert_eq!(
+                        arc.get_file_contents(
+                            "stream:/sound/bgm/bgm_test.nus3audio",
+                            Region::UsEnglish
+                        )
+                        .unwrap(),
+                        TEST_STREAM_CONTENTS
+                    );
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn read_at_replaced_data() {
+        use crate::{Compression, SharedFileMode};
+
+        let mut arc = ArcFile::from_read_at(test_arc_bytes()).unwrap();
+        arc.replace_file(
+            "fighter/mario/model.numdlb",
+            Region::UsEnglish,
+            &[0x42; 0x800],
+            Compression::Zstd,
+            SharedFileMode::Unshare,
+        )
+        .unwrap();
+
+        assert_eq!(
+            arc.get_file_contents("fighter/mario/model.numdlb", Region::UsEnglish)
+                .unwrap(),
+            &[0x42; 0x800][..]
+        );
+        assert_eq!(
+            arc.get_file_contents("fighter/mario/alias.numdlb", Region::UsEnglish)
+                .unwrap(),
+            TEST_FILE_CONTENTS
+        );
+
+        let mut writer = Cursor::new(Vec::new());
+        arc.write_to(&mut writer).unwrap();
+        let arc = ArcFile::from_reader(Cursor::new(writer.into_inner())).unwrap();
+        assert_eq!(
+            arc.get_file_contents("fighter/mario/model.numdlb", Region::UsEnglish)
+                .unwrap(),
+            &[0x42; 0x800][..]
+        );
+    }
 
     fn print_tree_hash(arc: &ArcFile, hash: Hash40, depth: usize) {
         for file in arc.get_dir_listing(hash).unwrap() {
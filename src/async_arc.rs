@@ -0,0 +1,279 @@
+use std::convert::TryInto;
+use std::io::{self, SeekFrom};
+use std::ops::Deref;
+use std::path::Path;
+
+use binrw::{BinReaderExt, BinResult};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+use tokio::sync::Mutex;
+
+use crate::arc_file::ARC_HEADER_SIZE;
+use crate::read_at::ReadAtCursor;
+use crate::{ArcFile, ArcLookup, FileData, Hash40, LookupError, ReadAt, Region, StreamData};
+
+/// Size of the header in front of each compressed table
+const COMP_TABLE_HEADER_SIZE: u64 = 0x10;
+
+/// An [`ArcFile`] read from an async source, for use within a tokio runtime.
+///
+/// Only the header and tables are read when opening, the same as [`ArcFile::open`]. The tables
+/// are kept in memory, so lookups such as [`ArcLookup::get_file_metadata`] are available
+/// synchronously through [`Deref`]. Reading file contents is done using the async methods of
+/// this type, which decompress files on tokio's blocking thread pool rather than on the runtime.
+///
+/// **Note:** The arc can't be modified (such as by [`ArcFile::replace_file`]), and the sync
+/// methods for reading file contents of the underlying [`ArcFile`] will fail.
+pub struct AsyncArcFile<R> {
+    arc: ArcFile,
+    reader: Mutex<R>,
+}
+
+impl AsyncArcFile<tokio::fs::File> {
+    pub async fn open<P: AsRef<Path>>(path: P) -> BinResult<Self> {
+        Self::from_reader(tokio::fs::File::open(path).await?).await
+    }
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin + Send> AsyncArcFile<R> {
+    /// Parse the header and tables of an arc from an async reader. The compressed tables are
+    /// decompressed and parsed on tokio's blocking thread pool.
+    pub async fn from_reader(mut reader: R) -> BinResult<Self> {
+        let header = read_exact_at(&mut reader, 0, ARC_HEADER_SIZE).await?;
+        let table_offset =
+            |start: usize| u64::from_le_bytes(header[start..start + 8].try_into().unwrap());
+        let file_system_offset = table_offset(0x20);
+        let search_file_system_offset = table_offset(0x28);
+
+        let mut sections = Sections(Vec::new());
+        for offset in [file_system_offset, search_file_system_offset] {
+            let table_header = read_exact_at(&mut reader, offset, COMP_TABLE_HEADER_SIZE).await?;
+            let comp_size = u32::from_le_bytes(table_header[8..12].try_into().unwrap()) as u64;
+
+            let table =
+                read_exact_at(&mut reader, offset, COMP_TABLE_HEADER_SIZE + comp_size).await?;
+            sections.0.push((offset, table));
+        }
+        sections.0.push((0, header));
+
+        let arc =
+            spawn_blocking(move || ReadAtCursor::new(sections).read_le::<ArcFile>()).await??;
+
+        Ok(Self {
+            arc,
+            reader: Mutex::new(reader),
+        })
+    }
+
+    /// Async version of [`ArcLookup::get_file_contents`]
+    pub async fn get_file_contents<Hash: Into<Hash40>>(
+        &self,
+        hash: Hash,
+        region: Region,
+    ) -> Result<Vec<u8>, LookupError> {
+        let hash = hash.into();
+
+        match self.get_nonstream_file_contents(hash, region).await {
+            Err(LookupError::Missing) => self.get_stream_file_contents(hash, region).await,
+            result => result,
+        }
+    }
+
+    /// Async version of [`ArcLookup::get_nonstream_file_contents`]
+    pub async fn get_nonstream_file_contents<Hash: Into<Hash40>>(
+        &self,
+        hash: Hash,
+        region: Region,
+    ) -> Result<Vec<u8>, LookupError> {
+        let file_info = self.arc.get_file_info_from_hash(hash.into())?;
+        let folder_offset = self.arc.get_folder_offset(file_info, region);
+        let file_data = *self.arc.get_file_data(file_info, region);
+
+        self.read_file_data(&file_data, folder_offset).await
+    }
+
+    /// Async version of [`ArcLookup::get_stream_file_contents`]
+    pub async fn get_stream_file_contents<Hash: Into<Hash40>>(
+        &self,
+        hash: Hash,
+        region: Region,
+    ) -> Result<Vec<u8>, LookupError> {
+        let stream_data = *self.arc.get_stream_data(hash.into(), region)?;
+
+        self.read_stream_file_data(&stream_data).await
+    }
+
+    /// Async version of [`ArcLookup::read_file_data`]
+    pub async fn read_file_data(
+        &self,
+        file_data: &FileData,
+        folder_offset: u64,
+    ) -> Result<Vec<u8>, LookupError> {
+        let offset = folder_offset
+            + self.arc.get_file_section_offset()
+            + ((file_data.offset_in_folder as u64) << 2);
+
+        if file_data.flags.compressed() && !file_data.flags.use_zstd() {
+            return Err(LookupError::UnsupportedCompression);
+        }
+
+        let raw = self
+            .read_exact_at(offset, file_data.comp_size as u64)
+            .await?;
+
+        if file_data.flags.compressed() {
+            let decomp_size = file_data.decomp_size as usize;
+
+            spawn_blocking(move || {
+                let mut data = Vec::with_capacity(decomp_size);
+                crate::zstd_backend::copy_decode(&raw[..], &mut data)?;
+
+                Ok(data)
+            })
+            .await?
+        } else {
+            Ok(raw)
+        }
+    }
+
+    /// Async version of [`ArcLookup::read_stream_file_data`]
+    pub async fn read_stream_file_data(
+        &self,
+        file_data: &StreamData,
+    ) -> Result<Vec<u8>, LookupError> {
+        Ok(self.read_exact_at(file_data.offset, file_data.size).await?)
+    }
+
+    async fn read_exact_at(&self, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+        read_exact_at(&mut *self.reader.lock().await, offset, len).await
+    }
+
+    /// Get the underlying reader back, dropping the tables
+    pub fn into_inner(self) -> R {
+        self.reader.into_inner()
+    }
+}
+
+impl<R> Deref for AsyncArcFile<R> {
+    type Target = ArcFile;
+
+    fn deref(&self) -> &ArcFile {
+        &self.arc
+    }
+}
+
+async fn read_exact_at<R>(reader: &mut R, offset: u64, len: u64) -> io::Result<Vec<u8>>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    let mut data = vec![0; len as usize];
+    reader.seek(SeekFrom::Start(offset)).await?;
+    reader.read_exact(&mut data).await?;
+
+    Ok(data)
+}
+
+/// Run a CPU-bound task (decompression) on tokio's blocking thread pool
+async fn spawn_blocking<T, F>(task: F) -> io::Result<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(task)
+        .await
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+}
+
+/// The parts of an arc which have been read into memory, used for parsing the tables without
+/// reading the data in between them
+struct Sections(Vec<(u64, Vec<u8>)>);
+
+impl ReadAt for Sections {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        self.0
+            .iter()
+            .find(|(start, data)| (*start..*start + data.len() as u64).contains(&offset))
+            .map(|(start, data)| data.read_at(buf, offset - start))
+            .unwrap_or_else(|| {
+                Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "read outside of the arc's header and tables",
+                ))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arc_file::tests::{
+        test_arc, test_arc_bytes, TEST_FILE_CONTENTS, TEST_STREAM_CONTENTS,
+    };
+    use crate::{Compression, SharedFileMode};
+    use std::io::Cursor;
+
+    const MODEL: &str = "fighter/mario/model.numdlb";
+    const STREAM: &str = "stream:/sound/bgm/bgm_test.nus3audio";
+
+    #[tokio::test]
+    async fn read_contents() {
+        let arc = AsyncArcFile::from_reader(Cursor::new(test_arc_bytes()))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            arc.get_file_contents(MODEL, Region::UsEnglish)
+                .await
+                .unwrap(),
+            TEST_FILE_CONTENTS
+        );
+        assert_eq!(
+            arc.get_file_contents(STREAM, Region::UsEnglish)
+                .await
+                .unwrap(),
+            TEST_STREAM_CONTENTS
+        );
+        assert!(matches!(
+            arc.get_file_contents("fighter/luigi/model.numdlb", Region::UsEnglish)
+                .await,
+            Err(LookupError::Missing)
+        ));
+
+        // Metadata lookups remain synchronous
+        let metadata = arc.get_file_metadata(MODEL, Region::UsEnglish).unwrap();
+        assert_eq!(metadata.decomp_size, TEST_FILE_CONTENTS.len() as u64);
+    }
+
+    #[tokio::test]
+    async fn read_compressed_contents() {
+        let data = (0..0x10000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+
+        let mut sync_arc = test_arc();
+        sync_arc
+            .replace_file(
+                MODEL,
+                Region::UsEnglish,
+                &data,
+                Compression::Zstd,
+                SharedFileMode::Unshare,
+            )
+            .unwrap();
+
+        let mut bytes = Cursor::new(Vec::new());
+        sync_arc.write_to(&mut bytes).unwrap();
+        bytes.set_position(0);
+
+        let arc = AsyncArcFile::from_reader(bytes).await.unwrap();
+        assert_eq!(
+            arc.get_file_contents(MODEL, Region::UsEnglish)
+                .await
+                .unwrap(),
+            data
+        );
+        assert_eq!(
+            arc.get_file_contents("fighter/mario/alias.numdlb", Region::UsEnglish)
+                .await
+                .unwrap(),
+            TEST_FILE_CONTENTS
+        );
+    }
+}
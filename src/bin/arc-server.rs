@@ -0,0 +1,35 @@
+//! Serve a data.arc over TCP for use with `ArcFile::open_over_network`
+//!
+//! Usage: `arc-server <data.arc> [address]`, where `address` defaults to `0.0.0.0:43022`
+
+use std::fs::File;
+use std::io::BufReader;
+use std::process::exit;
+
+use smash_arc::{NetworkServer, DEFAULT_PORT};
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let path = match args.next() {
+        Some(path) => path,
+        None => {
+            eprintln!("Usage: arc-server <data.arc> [address]");
+            exit(1);
+        }
+    };
+    let addr = args
+        .next()
+        .unwrap_or_else(|| format!("0.0.0.0:{}", DEFAULT_PORT));
+
+    let result = File::open(&path)
+        .and_then(|file| NetworkServer::bind(BufReader::new(file), &addr[..]))
+        .and_then(|server| {
+            println!("Serving {} on {}", path, server.local_addr()?);
+            server.serve()
+        });
+
+    if let Err(err) = result {
+        eprintln!("Error: {}", err);
+        exit(1);
+    }
+}
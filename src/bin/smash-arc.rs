@@ -0,0 +1,271 @@
+//! Command-line tool for listing, extracting and inspecting the contents of a data.arc
+
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::exit;
+
+use smash_arc::{
+    hash40, label_to_path, ArcFile, ArcLookup, ExtractOptions, ExtractProgress, FileNode, Hash40,
+    HashLabels, LookupError, Region,
+};
+use structopt::StructOpt;
+
+const DEFAULT_LABELS: &str = "hash_labels.txt";
+
+#[derive(StructOpt)]
+#[structopt(
+    name = "smash-arc",
+    about = "List, extract and inspect Smash Ultimate's data.arc"
+)]
+struct Args {
+    /// Path to the data.arc
+    #[structopt(short, long, default_value = "data.arc")]
+    arc: PathBuf,
+
+    /// Hash labels file used for file names [default: hash_labels.txt, if present]
+    #[structopt(short, long)]
+    labels: Option<PathBuf>,
+
+    /// Region to use for regional files
+    #[structopt(short, long, default_value = "us_en", parse(try_from_str = parse_region))]
+    region: Region,
+
+    #[structopt(subcommand)]
+    command: Command,
+}
+
+#[derive(StructOpt)]
+enum Command {
+    /// List the contents of a directory
+    Ls {
+        #[structopt(default_value = "/")]
+        dir: String,
+    },
+    /// Recursively list the contents of a directory
+    Tree {
+        #[structopt(default_value = "/")]
+        dir: String,
+    },
+    /// Extract a file, or every file within a directory
+    Extract {
+        path: String,
+
+        /// Directory to extract to
+        #[structopt(short, long, default_value = ".")]
+        out: PathBuf,
+    },
+    /// Print the metadata of a file
+    Info { path: String },
+    /// List the files sharing data with a file
+    Shared { path: String },
+    /// Convert the labels file to the binary label format, which loads faster
+    ConvertLabels {
+        /// Where to write the binary labels
+        out: PathBuf,
+    },
+}
+
+fn parse_region(region: &str) -> Result<Region, String> {
+    match region.parse() {
+        Ok(Region::None) | Err(_) => Err(format!("unknown region '{}'", region)),
+        Ok(region) => Ok(region),
+    }
+}
+
+/// Parse a path, or a hash given as hex (`0x...`)
+fn parse_hash(path: &str) -> Hash40 {
+    path.parse()
+        .unwrap_or_else(|_| match path.trim_end_matches('/') {
+            "" => hash40("/"),
+            path => hash40(path),
+        })
+}
+
+/// The label of a hash, or the hash in hex if it has no label
+fn name(arc: &ArcFile, hash: Hash40) -> String {
+    arc.label(hash).unwrap_or_else(|| hash.to_string())
+}
+
+fn file_name(arc: &ArcFile, hash: Hash40) -> String {
+    let name = name(arc, hash);
+
+    match name.trim_end_matches('/').rsplit_once('/') {
+        Some((_, file_name)) if !file_name.is_empty() => file_name.to_owned(),
+        _ => name,
+    }
+}
+
+fn listing(arc: &ArcFile, dir: Hash40) -> Result<&[FileNode], LookupError> {
+    arc.get_dir_listing(dir).ok_or(LookupError::Missing)
+}
+
+fn print_tree(arc: &ArcFile, dir: Hash40, depth: usize) -> Result<(), LookupError> {
+    for node in listing(arc, dir)? {
+        match *node {
+            FileNode::Dir(dir) => {
+                println!("{:indent$}{}/", "", file_name(arc, dir), indent = depth * 2);
+                print_tree(arc, dir, depth + 1)?;
+            }
+            FileNode::File(file) => {
+                println!("{:indent$}{}", "", file_name(arc, file), indent = depth * 2)
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn is_file(arc: &ArcFile, hash: Hash40) -> bool {
+    arc.get_file_path_index_from_hash(hash).is_ok() || arc.get_stream_entry(hash).is_ok()
+}
+
+/// Where to extract a file to within `out`. Files whose label would be written outside of `out`
+/// are named by their hash, the same as unlabeled files.
+fn output_path(arc: &ArcFile, out: &Path, file: Hash40) -> PathBuf {
+    match arc.label(file).as_deref().and_then(label_to_path) {
+        Some(path) => out.join(path),
+        None => out.join(format!("{}.bin", file)),
+    }
+}
+
+fn extract(
+    arc: &ArcFile,
+    hash: Hash40,
+    out: &Path,
+    region: Region,
+) -> Result<usize, Box<dyn Error>> {
+    if !is_file(arc, hash) {
+        let mut print_progress = |progress: ExtractProgress| {
+            println!(
+                "[{}/{}] {}",
+                progress.extracted,
+                progress.total,
+                progress.path.display()
+            )
+        };
+        let options = ExtractOptions {
+            progress: Some(&mut print_progress),
+            ..Default::default()
+        };
+
+        return Ok(arc.extract_dir(hash, out, region, options)?);
+    }
+
+    let path = output_path(arc, out, hash);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Stream the contents to disk, as stream files can be hundreds of megabytes
+    let mut reader = arc.open_file(hash, region)?;
+    let mut writer = BufWriter::new(File::create(&path)?);
+    io::copy(&mut reader, &mut writer)?;
+    writer.flush()?;
+    println!("{}", path.display());
+
+    Ok(1)
+}
+
+fn print_info(arc: &ArcFile, hash: Hash40, region: Region) -> Result<(), LookupError> {
+    let metadata = arc.get_file_metadata(hash, region)?;
+
+    println!("path:        {}", name(arc, metadata.path_hash));
+    println!("parent:      {}", name(arc, metadata.parent_hash));
+    println!("file name:   {}", name(arc, metadata.file_name_hash));
+    println!("extension:   {}", name(arc, metadata.ext_hash));
+    println!("offset:      {:#x}", metadata.offset);
+    println!("comp size:   {:#x}", metadata.comp_size);
+    println!("decomp size: {:#x}", metadata.decomp_size);
+    println!("stream:      {}", metadata.is_stream);
+    println!("shared:      {}", metadata.is_shared);
+    println!("redirect:    {}", metadata.is_redirect);
+    println!("regional:    {}", metadata.is_regional);
+    println!("localized:   {}", metadata.is_localized);
+    println!("compressed:  {}", metadata.is_compressed);
+    println!("zstd:        {}", metadata.uses_zstd);
+
+    Ok(())
+}
+
+/// The labels file given, or the default one if it exists
+fn labels_path(labels: Option<&Path>) -> Option<&Path> {
+    match labels {
+        Some(labels) => Some(labels),
+        None if Path::new(DEFAULT_LABELS).exists() => Some(Path::new(DEFAULT_LABELS)),
+        None => None,
+    }
+}
+
+/// Load the labels (which are only used for naming files and directories) and open the arc
+fn open_arc(path: &Path, labels: Option<&Path>) -> Result<ArcFile, Box<dyn Error>> {
+    match labels_path(labels) {
+        Some(labels) => Hash40::set_global_labels_file(labels)?,
+        None => eprintln!("Warning: no labels file found, names are shown as hashes"),
+    }
+
+    Ok(ArcFile::open(path)?)
+}
+
+fn run(args: Args) -> Result<(), Box<dyn Error>> {
+    let Args {
+        arc: arc_path,
+        labels,
+        region,
+        command,
+    } = args;
+    let labels = labels.as_deref();
+
+    match command {
+        Command::Ls { dir } => {
+            let arc = open_arc(&arc_path, labels)?;
+            for node in listing(&arc, parse_hash(&dir))? {
+                match *node {
+                    FileNode::Dir(dir) => println!("{}/", file_name(&arc, dir)),
+                    FileNode::File(file) => println!("{}", file_name(&arc, file)),
+                }
+            }
+        }
+        Command::Tree { dir } => {
+            let arc = open_arc(&arc_path, labels)?;
+            print_tree(&arc, parse_hash(&dir), 0)?
+        }
+        Command::Extract { path, out } => {
+            let arc = open_arc(&arc_path, labels)?;
+            let count = extract(&arc, parse_hash(&path), &out, region)?;
+            eprintln!("Extracted {} file(s)", count);
+        }
+        Command::Info { path } => {
+            let arc = open_arc(&arc_path, labels)?;
+            print_info(&arc, parse_hash(&path), region)?
+        }
+        Command::Shared { path } => {
+            let arc = open_arc(&arc_path, labels)?;
+            for file in arc.get_shared_files(parse_hash(&path), region)? {
+                println!("{}", name(&arc, file));
+            }
+        }
+        Command::ConvertLabels { out } => {
+            let labels = labels_path(labels).ok_or_else(|| {
+                format!(
+                    "no labels file to convert, pass one using --labels or add {}",
+                    DEFAULT_LABELS
+                )
+            })?;
+            let labels = HashLabels::from_file(labels)?;
+
+            let mut writer = BufWriter::new(File::create(out)?);
+            labels.write_binary(&mut writer)?;
+            writer.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = run(Args::from_args()) {
+        eprintln!("Error: {}", err);
+        exit(1);
+    }
+}
@@ -0,0 +1,349 @@
+//! Cracking unknown [`Hash40`]s by generating candidate labels from templates and wordlists
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+use rayon::prelude::*;
+use thiserror::Error;
+
+use crate::{Hash40, Hash40Builder, HashLabels};
+
+/// Hash40s store the length of the string in a single byte
+const MAX_LEN: usize = 0xFF;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum CrackError {
+    #[error("unclosed '{{' in template")]
+    UnclosedBrace,
+
+    #[error("invalid range '{{{0}}}' in template, expected a range such as '{{00..07}}'")]
+    InvalidRange(String),
+
+    #[error("template uses the wordlist '{0}', which hasn't been added")]
+    MissingWordlist(String),
+}
+
+/// A part of a [`Template`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    /// Every number in the range (inclusive), padded with zeroes to `width` digits
+    Range {
+        start: u32,
+        end: u32,
+        width: usize,
+    },
+    Wordlist(String),
+}
+
+/// A pattern for generating candidate labels, such as
+/// `fighter/{name}/model/body/c{00..07}/{word}.{ext}`.
+///
+/// `{start..end}` is replaced with each number in the range, padded with zeroes to the length of
+/// `start`. `{name}` is replaced with each word in the wordlist of that name (see
+/// [`Cracker::add_wordlist`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Self, CrackError> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+
+        while let Some(open) = rest.find('{') {
+            if open > 0 {
+                parts.push(Part::Literal(rest[..open].to_owned()));
+            }
+
+            let close = rest[open..].find('}').ok_or(CrackError::UnclosedBrace)? + open;
+            let inner = &rest[open + 1..close];
+            parts.push(match inner.split_once("..") {
+                Some((start, end)) => parse_range(start, end)
+                    .ok_or_else(|| CrackError::InvalidRange(inner.to_owned()))?,
+                None => Part::Wordlist(inner.to_owned()),
+            });
+
+            rest = &rest[close + 1..];
+        }
+
+        if !rest.is_empty() {
+            parts.push(Part::Literal(rest.to_owned()));
+        }
+
+        Ok(Self { parts })
+    }
+}
+
+fn parse_range(start: &str, end: &str) -> Option<Part> {
+    Some(Part::Range {
+        start: start.parse().ok()?,
+        end: end.parse().ok()?,
+        width: start.len(),
+    })
+}
+
+/// The candidates for a single part of a template, along with the range of their lengths
+struct Slot<'a> {
+    candidates: Vec<Cow<'a, str>>,
+    min_len: usize,
+    max_len: usize,
+}
+
+/// Cracks a set of unknown hashes by hashing every label generated from a [`Template`], using
+/// multiple threads.
+///
+/// Candidates are hashed incrementally, so labels sharing a prefix only hash it once. As a
+/// [`Hash40`] includes the length of its label, candidates which can't reach the length of any
+/// unknown hash are skipped without being hashed.
+pub struct Cracker {
+    targets: HashSet<Hash40>,
+    /// Whether any target has a label of each length
+    target_lengths: [bool; MAX_LEN + 1],
+    wordlists: HashMap<String, Vec<String>>,
+}
+
+impl Cracker {
+    pub fn new<I: IntoIterator<Item = Hash40>>(targets: I) -> Self {
+        let targets: HashSet<Hash40> = targets.into_iter().collect();
+        let mut target_lengths = [false; MAX_LEN + 1];
+        for target in &targets {
+            target_lengths[target.len() as usize] = true;
+        }
+
+        Self {
+            targets,
+            target_lengths,
+            wordlists: HashMap::new(),
+        }
+    }
+
+    /// Add a list of words which can be used in templates as `{name}`, replacing any existing
+    /// list of the same name
+    pub fn add_wordlist<S, I>(&mut self, name: &str, words: I) -> &mut Self
+    where
+        S: Into<String>,
+        I: IntoIterator<Item = S>,
+    {
+        let mut words: Vec<String> = words.into_iter().map(Into::into).collect();
+        words.sort_unstable();
+        words.dedup();
+        self.wordlists.insert(name.to_owned(), words);
+
+        self
+    }
+
+    /// Hash every label generated from `template`, adding each one matching an unknown hash to
+    /// `labels`. Returns the labels which were found, in sorted order.
+    pub fn crack(
+        &self,
+        template: &Template,
+        labels: &mut HashLabels,
+    ) -> Result<Vec<String>, CrackError> {
+        let slots = self.slots(template)?;
+
+        // The shortest and longest the rest of a label can be after each slot
+        let mut remaining = vec![(0, 0); slots.len() + 1];
+        for (i, slot) in slots.iter().enumerate().rev() {
+            remaining[i] = (
+                remaining[i + 1].0 + slot.min_len,
+                remaining[i + 1].1 + slot.max_len,
+            );
+        }
+
+        let search = Search {
+            cracker: self,
+            slots: &slots,
+            remaining: &remaining,
+        };
+
+        // Leading slots with a single candidate are only hashed once, then the candidates of the
+        // first slot with more are split between threads
+        let split = slots
+            .iter()
+            .position(|slot| slot.candidates.len() != 1)
+            .unwrap_or(slots.len());
+        let mut hasher = Hash40Builder::new();
+        let mut prefix = String::with_capacity(MAX_LEN);
+        for slot in &slots[..split] {
+            hasher.push_str(&slot.candidates[0]);
+            prefix.push_str(&slot.candidates[0]);
+        }
+
+        let mut found = match slots.get(split) {
+            None => {
+                let mut found = Vec::new();
+                search.search(split, &hasher, &mut prefix, &mut found);
+                found
+            }
+            Some(slot) => slot
+                .candidates
+                .par_iter()
+                .flat_map_iter(|candidate| {
+                    let mut found = Vec::new();
+                    let mut hasher = hasher.clone();
+                    hasher.push_str(candidate);
+
+                    let mut label = prefix.clone();
+                    label.push_str(candidate);
+                    search.search(split + 1, &hasher, &mut label, &mut found);
+
+                    found
+                })
+                .collect(),
+        };
+        found.sort_unstable();
+        found.dedup();
+
+        for label in &found {
+            // An unknown hash may have been labeled since, in which case the label is kept
+            let _ = labels.insert(&label[..]);
+        }
+
+        Ok(found)
+    }
+
+    fn slots<'a>(&'a self, template: &'a Template) -> Result<Vec<Slot<'a>>, CrackError> {
+        template
+            .parts
+            .iter()
+            .map(|part| {
+                let candidates: Vec<Cow<'a, str>> = match part {
+                    Part::Literal(literal) => vec![literal.as_str().into()],
+                    Part::Range { start, end, width } => (*start..=*end)
+                        .map(|i| format!("{:0width$}", i, width = width).into())
+                        .collect(),
+                    Part::Wordlist(name) => self
+                        .wordlists
+                        .get(name)
+                        .ok_or_else(|| CrackError::MissingWordlist(name.clone()))?
+                        .iter()
+                        .map(|word| word.as_str().into())
+                        .collect(),
+                };
+
+                Ok(Slot {
+                    min_len: candidates.iter().map(|c| c.len()).min().unwrap_or(0),
+                    max_len: candidates.iter().map(|c| c.len()).max().unwrap_or(0),
+                    candidates,
+                })
+            })
+            .collect()
+    }
+}
+
+struct Search<'a> {
+    cracker: &'a Cracker,
+    slots: &'a [Slot<'a>],
+    remaining: &'a [(usize, usize)],
+}
+
+impl Search<'_> {
+    /// Extend `label` (hashed so far by `hasher`) with each candidate of the slot at `index`
+    fn search(
+        &self,
+        index: usize,
+        hasher: &Hash40Builder,
+        label: &mut String,
+        found: &mut Vec<String>,
+    ) {
+        let (min_rest, max_rest) = self.remaining[index];
+        let min_len = label.len() + min_rest;
+        let max_len = (label.len() + max_rest).min(MAX_LEN);
+        if min_len > max_len
+            || !self.cracker.target_lengths[min_len..=max_len]
+                .iter()
+                .any(|len| *len)
+        {
+            return;
+        }
+
+        let slot = match self.slots.get(index) {
+            Some(slot) => slot,
+            None => {
+                let hash = hasher.finish();
+                if self.cracker.targets.contains(&hash) {
+                    found.push(label.clone());
+                }
+
+                return;
+            }
+        };
+
+        for candidate in &slot.candidates {
+            let mut hasher = hasher.clone();
+            hasher.push_str(candidate);
+
+            let len = label.len();
+            label.push_str(candidate);
+            self.search(index + 1, &hasher, label, found);
+            label.truncate(len);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash40;
+
+    #[test]
+    fn parse_template() {
+        let template = Template::parse("fighter/{name}/c{00..07}.{ext}").unwrap();
+        assert_eq!(
+            template.parts,
+            [
+                Part::Literal("fighter/".to_owned()),
+                Part::Wordlist("name".to_owned()),
+                Part::Literal("/c".to_owned()),
+                Part::Range {
+                    start: 0,
+                    end: 7,
+                    width: 2
+                },
+                Part::Literal(".".to_owned()),
+                Part::Wordlist("ext".to_owned()),
+            ]
+        );
+
+        assert_eq!(
+            Template::parse("fighter/{name"),
+            Err(CrackError::UnclosedBrace)
+        );
+        assert_eq!(
+            Template::parse("c{00..x}"),
+            Err(CrackError::InvalidRange("00..x".to_owned()))
+        );
+    }
+
+    #[test]
+    fn crack_templates() {
+        let targets = [
+            "fighter/mario/model/body/c03/model.numdlb",
+            "fighter/luigi/model/body/c00/metamon_model.numdlb",
+            "fighter/mario/model/body/c07/model.nutexb",
+        ];
+        let mut cracker = Cracker::new(targets.iter().map(|target| hash40(target)));
+        cracker
+            .add_wordlist("name", ["mario", "luigi", "peach"])
+            .add_wordlist("word", ["model", "metamon_model", "def"])
+            .add_wordlist("ext", ["numdlb", "nutexb"]);
+
+        let template = Template::parse("fighter/{name}/model/body/c{00..07}/{word}.{ext}").unwrap();
+        let mut labels = HashLabels::new();
+        let mut expected = targets.to_vec();
+        expected.sort_unstable();
+
+        assert_eq!(cracker.crack(&template, &mut labels).unwrap(), expected);
+        for target in targets {
+            assert_eq!(hash40(target).label(&labels), Some(target));
+        }
+
+        let template = Template::parse("fighter/{missing}").unwrap();
+        assert_eq!(
+            cracker.crack(&template, &mut labels),
+            Err(CrackError::MissingWordlist("missing".to_owned()))
+        );
+    }
+}
@@ -0,0 +1,324 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::{hash40, ArcFile, ArcLookup, FileNode, Hash40, LookupError, Region};
+
+#[cfg(feature = "parallel")]
+mod parallel;
+
+/// The progress of an extraction started by [`ArcFile::extract_dir`], passed to
+/// [`ExtractOptions::progress`] after each file is written or skipped. Once every file has been
+/// reported, `extracted + skipped` is equal to `total`.
+#[derive(Debug, Clone, Copy)]
+pub struct ExtractProgress<'a> {
+    /// The file which was just extracted or skipped
+    pub hash: Hash40,
+    /// Where the file was written to
+    pub path: &'a Path,
+    /// The number of files extracted so far, including this one
+    pub extracted: usize,
+    /// The number of files skipped so far because of an error, including this one
+    pub skipped: usize,
+    /// The total number of files being extracted
+    pub total: usize,
+    /// The error this file was skipped because of, if it was skipped
+    pub error: Option<&'a LookupError>,
+}
+
+/// Options for [`ArcFile::extract_dir`]
+#[derive(Default)]
+pub struct ExtractOptions<'a> {
+    /// Called after each file is written or skipped, for reporting progress
+    pub progress: Option<&'a mut dyn FnMut(ExtractProgress<'_>)>,
+
+    /// Skip files which fail to be read or written rather than stopping the extraction. Skipped
+    /// files are still passed to `progress`, along with the error.
+    pub skip_errors: bool,
+}
+
+/// The path a label is extracted to relative to the destination, with the `:` of `stream:`
+/// removed. Returns `None` for labels which could be written outside of the destination, such as
+/// labels containing `..` or starting with `/`.
+pub fn label_to_path(label: &str) -> Option<PathBuf> {
+    let label = label.replace(':', "");
+    let mut path = PathBuf::new();
+
+    for component in Path::new(&label).components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => (),
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+
+    Some(path).filter(|path| !path.as_os_str().is_empty())
+}
+
+impl ArcFile {
+    /// Recursively extract every file within a directory (including stream files) to `dest`,
+    /// using [`ArcFile::get_dir_listing`] to walk the directory tree. Passing `"/"` extracts the
+    /// entire arc.
+    ///
+    /// Files are written to their labeled path within `dest`. Files and directories without a
+    /// label are named by their hash in hex (`0x...`). Returns the number of files extracted.
+    ///
+    /// **Note:** Labels (see [`ArcFile::labels`]) are only used for naming, so they may be loaded
+    /// after the arc is opened.
+    pub fn extract_dir<Hash: Into<Hash40>>(
+        &self,
+        hash: Hash,
+        dest: &Path,
+        region: Region,
+        mut options: ExtractOptions<'_>,
+    ) -> Result<usize, LookupError> {
+        let files = self.files_to_extract(hash.into())?;
+
+        let total = files.len();
+        let mut extracted = 0;
+        let mut skipped = 0;
+        for (hash, path) in files {
+            let path = dest.join(path);
+
+            let result = self.get_file_contents(hash, region).and_then(|contents| {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+
+                fs::write(&path, contents).map_err(LookupError::from)
+            });
+
+            let error = match result {
+                Ok(()) => {
+                    extracted += 1;
+                    None
+                }
+                Err(err) if options.skip_errors => {
+                    skipped += 1;
+                    Some(err)
+                }
+                Err(err) => return Err(err),
+            };
+
+            if let Some(progress) = options.progress.as_mut() {
+                progress(ExtractProgress {
+                    hash,
+                    path: &path,
+                    extracted,
+                    skipped,
+                    total,
+                    error: error.as_ref(),
+                });
+            }
+        }
+
+        Ok(extracted)
+    }
+
+    /// Every file within a directory, along with where to extract it to relative to the
+    /// destination
+    fn files_to_extract(&self, dir: Hash40) -> Result<Vec<(Hash40, PathBuf)>, LookupError> {
+        let mut files = Vec::new();
+        self.collect_files(dir, &self.relative_path(dir, Path::new("")), &mut files)?;
+
+        Ok(files)
+    }
+
+    /// Where to extract a file or directory relative to the destination. Labeled hashes are placed
+    /// at their label (see [`label_to_path`]), unlabeled ones (or ones whose label would escape
+    /// the destination) are named by their hash within their parent directory.
+    fn relative_path(&self, hash: Hash40, parent: &Path) -> PathBuf {
+        if hash == hash40("/") {
+            return PathBuf::new();
+        }
+
+        match self.label(hash).and_then(|label| label_to_path(&label)) {
+            Some(path) => path,
+            None => parent.join(hash.to_string()),
+        }
+    }
+
+    fn collect_files(
+        &self,
+        dir: Hash40,
+        dir_path: &Path,
+        files: &mut Vec<(Hash40, PathBuf)>,
+    ) -> Result<(), LookupError> {
+        for node in self.get_dir_listing(dir).ok_or(LookupError::Missing)? {
+            match *node {
+                FileNode::Dir(child) => {
+                    self.collect_files(child, &self.relative_path(child, dir_path), files)?
+                }
+                FileNode::File(file) => files.push((file, self.relative_path(file, dir_path))),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arc_file::tests::{test_arc, TEST_FILE_CONTENTS, TEST_STREAM_CONTENTS};
+    use crate::HashLabels;
+    use parking_lot::RwLock;
+    use std::sync::Arc;
+
+    pub(super) fn test_dest(name: &str) -> PathBuf {
+        let dest =
+            std::env::temp_dir().join(format!("smash-arc-extract-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dest);
+
+        dest
+    }
+
+    /// The test arc with its own labels, so the global labels used by other tests are untouched
+    pub(super) fn labeled_test_arc() -> ArcFile {
+        let labels = HashLabels::from_string(
+            "fighter/mario\n\
+             fighter/mario/model.numdlb\n\
+             fighter/mario/alias.numdlb\n\
+             stream:/sound/bgm/bgm_test.nus3audio",
+        );
+
+        let mut arc = test_arc();
+        arc.set_labels(Some(Arc::new(RwLock::new(labels))));
+
+        arc
+    }
+
+    #[test]
+    fn extract_everything() {
+        let arc = labeled_test_arc();
+        let dest = test_dest("all");
+
+        let mut reported = Vec::new();
+        let extracted = arc
+            .extract_dir(
+                "/",
+                &dest,
+                Region::UsEnglish,
+                ExtractOptions {
+                    progress: Some(&mut |progress: ExtractProgress| {
+                        assert_eq!(progress.total, 3);
+                        reported.push((progress.extracted, progress.path.to_owned()));
+                    }),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        assert_eq!(extracted, 3);
+        assert_eq!(
+            reported.iter().map(|(count, _)| *count).collect::<Vec<_>>(),
+            [1, 2, 3]
+        );
+        assert!(reported.iter().all(|(_, path)| path.starts_with(&dest)));
+
+        let model = fs::read(dest.join("fighter/mario/model.numdlb")).unwrap();
+        let stream = fs::read(dest.join("stream/sound/bgm/bgm_test.nus3audio")).unwrap();
+        assert_eq!(model, TEST_FILE_CONTENTS);
+        assert_eq!(stream, TEST_STREAM_CONTENTS);
+
+        fs::remove_dir_all(&dest).unwrap();
+    }
+
+    /// Extract to a destination where the fighter directory is blocked by a file, so every file
+    /// except the stream file fails to be written
+    pub(super) fn blocked_test_dest(name: &str) -> PathBuf {
+        let dest = test_dest(name);
+        fs::create_dir_all(&dest).unwrap();
+        fs::write(dest.join("fighter"), b"").unwrap();
+
+        dest
+    }
+
+    #[test]
+    fn extract_skipping_errors() {
+        let arc = labeled_test_arc();
+        let dest = blocked_test_dest("skip");
+
+        let mut last = None;
+        let mut errors = 0;
+        let extracted = arc
+            .extract_dir(
+                "/",
+                &dest,
+                Region::UsEnglish,
+                ExtractOptions {
+                    progress: Some(&mut |progress: ExtractProgress| {
+                        errors += progress.error.is_some() as usize;
+                        last = Some((progress.extracted, progress.skipped, progress.total));
+                    }),
+                    skip_errors: true,
+                },
+            )
+            .unwrap();
+
+        assert_eq!(extracted, 1);
+        assert_eq!(errors, 2);
+        assert_eq!(last, Some((1, 2, 3)));
+        assert!(dest.join("stream/sound/bgm/bgm_test.nus3audio").exists());
+
+        fs::remove_dir_all(&dest).unwrap();
+    }
+
+    #[test]
+    fn extract_subdirectory() {
+        let arc = labeled_test_arc();
+        let dest = test_dest("subdir");
+
+        let extracted = arc
+            .extract_dir(
+                "fighter/mario",
+                &dest,
+                Region::UsEnglish,
+                Default::default(),
+            )
+            .unwrap();
+
+        assert_eq!(extracted, 2);
+        assert!(dest.join("fighter/mario/alias.numdlb").exists());
+        assert!(!dest.join("stream").exists());
+
+        fs::remove_dir_all(&dest).unwrap();
+    }
+
+    #[test]
+    fn unlabeled_paths() {
+        let arc = crate::arc_file::tests::test_arc();
+        let hash = Hash40::from(0x12_3456_789A);
+
+        assert_eq!(
+            arc.relative_path(hash, Path::new("fighter/mario")),
+            Path::new("fighter/mario/0x123456789a")
+        );
+        assert_eq!(arc.relative_path(hash40("/"), Path::new("")), Path::new(""));
+    }
+
+    #[test]
+    fn unsafe_labels() {
+        assert_eq!(
+            label_to_path("stream:/sound/./bgm").unwrap(),
+            Path::new("stream/sound/bgm")
+        );
+        for label in [
+            "../model.numdlb",
+            "fighter/../../model.numdlb",
+            "/etc/passwd",
+            "",
+        ] {
+            assert_eq!(label_to_path(label), None);
+        }
+
+        let mut arc = test_arc();
+        let labels = HashLabels::from_string("../../escaped.numdlb");
+        arc.set_labels(Some(Arc::new(RwLock::new(labels))));
+
+        let hash = hash40("../../escaped.numdlb");
+        assert_eq!(
+            arc.relative_path(hash, Path::new("fighter")),
+            Path::new("fighter").join(hash.to_string())
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use std::fs;
+use std::io::{self, Read, SeekFrom};
+use std::path::Path;
+use std::sync::mpsc;
+
+use rayon::prelude::*;
+
+use super::{ExtractOptions, ExtractProgress};
+use crate::{ArcFile, Hash40, LookupError, Region, SeekRead};
+
+impl ArcFile {
+    /// Same as [`ArcFile::extract_dir`], but reads and decompresses files in parallel on the
+    /// rayon thread pool, using separate readers rather than sharing [`ArcFile::reader`].
+    ///
+    /// `open_reader` is called for each batch of files to create an independent reader over the
+    /// same data.arc this was opened from, such as `|| File::open("data.arc").map(BufReader::new)`.
+    /// It may be called many times per thread, so it should be cheap to call. Any data replaced
+    /// in memory is still applied on top of these readers.
+    ///
+    /// The files written are identical to those written by [`ArcFile::extract_dir`], however
+    /// progress is reported in the order files finish extracting. If `skip_errors` is not set,
+    /// files which were already being extracted when an error occurs are still written.
+    pub fn extract_dir_parallel<Hash, Open, R>(
+        &self,
+        hash: Hash,
+        dest: &Path,
+        region: Region,
+        mut options: ExtractOptions<'_>,
+        open_reader: Open,
+    ) -> Result<usize, LookupError>
+    where
+        Hash: Into<Hash40>,
+        Open: Fn() -> io::Result<R> + Sync,
+        R: SeekRead,
+    {
+        let files = self.files_to_extract(hash.into())?;
+        let total = files.len();
+
+        let (sender, receiver) = mpsc::channel();
+        let mut extracted = 0;
+        let mut skipped = 0;
+
+        let open_reader = &open_reader;
+        let result = std::thread::scope(|scope| {
+            let workers = scope.spawn(move || {
+                files
+                    .into_par_iter()
+                    .map_init(
+                        || open_reader().map(|reader| self.patch_reader(reader)),
+                        |reader, (hash, path)| {
+                            let path = dest.join(path);
+                            let result = match reader {
+                                Ok(reader) => self.extract_file(reader, hash, region, &path),
+                                Err(err) => Err(io::Error::new(err.kind(), err.to_string()).into()),
+                            };
+
+                            sender.send((hash, path, result)).map_err(|_| {
+                                LookupError::from(io::Error::from(io::ErrorKind::Interrupted))
+                            })
+                        },
+                    )
+                    .collect::<Result<(), LookupError>>()
+            });
+
+            for (hash, path, result) in receiver {
+                let error = match result {
+                    Ok(()) => {
+                        extracted += 1;
+                        None
+                    }
+                    Err(err) if options.skip_errors => {
+                        skipped += 1;
+                        Some(err)
+                    }
+                    // Dropping the receiver stops the workers once they finish their current file
+                    Err(err) => return Err(err),
+                };
+
+                if let Some(progress) = options.progress.as_mut() {
+                    progress(ExtractProgress {
+                        hash,
+                        path: &path,
+                        extracted,
+                        skipped,
+                        total,
+                        error: error.as_ref(),
+                    });
+                }
+            }
+
+            workers.join().unwrap()
+        });
+
+        result.map(|()| extracted)
+    }
+
+    fn extract_file(
+        &self,
+        reader: &mut Box<dyn SeekRead + '_>,
+        hash: Hash40,
+        region: Region,
+        path: &Path,
+    ) -> Result<(), LookupError> {
+        let location = self.data_location(hash, region)?;
+
+        reader.seek(SeekFrom::Start(location.offset))?;
+        let mut reader = Read::take(reader, location.size);
+        let mut data = Vec::with_capacity(location.size as usize);
+
+        if location.compressed {
+            crate::zstd_backend::copy_decode(reader, &mut data)?;
+        } else if reader.read_to_end(&mut data)? as u64 != location.size {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Failed to read data").into());
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, data)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{blocked_test_dest, labeled_test_arc, test_dest};
+    use super::*;
+    use crate::arc_file::tests::test_arc_bytes;
+    use crate::{Compression, SharedFileMode};
+    use binrw::io::Cursor;
+    use std::path::PathBuf;
+
+    fn read_tree(root: &Path) -> Vec<(PathBuf, Vec<u8>)> {
+        let mut files = Vec::new();
+        let mut dirs = vec![root.to_owned()];
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else {
+                    files.push((
+                        path.strip_prefix(root).unwrap().to_owned(),
+                        fs::read(&path).unwrap(),
+                    ));
+                }
+            }
+        }
+        files.sort();
+
+        files
+    }
+
+    #[test]
+    fn matches_serial_extraction() {
+        let mut arc = labeled_test_arc();
+        arc.replace_file(
+            "fighter/mario/model.numdlb",
+            Region::UsEnglish,
+            &[0x42; 0x800],
+            Compression::Zstd,
+            SharedFileMode::Unshare,
+        )
+        .unwrap();
+
+        let serial = test_dest("serial");
+        let parallel = test_dest("parallel");
+
+        let serial_count = arc
+            .extract_dir("/", &serial, Region::UsEnglish, Default::default())
+            .unwrap();
+
+        let mut reported = 0;
+        let parallel_count = arc
+            .extract_dir_parallel(
+                "/",
+                &parallel,
+                Region::UsEnglish,
+                ExtractOptions {
+                    progress: Some(&mut |progress: ExtractProgress| {
+                        reported += 1;
+                        assert_eq!(progress.extracted, reported);
+                        assert_eq!(progress.total, 3);
+                    }),
+                    ..Default::default()
+                },
+                || Ok(Cursor::new(test_arc_bytes())),
+            )
+            .unwrap();
+
+        assert_eq!(serial_count, 3);
+        assert_eq!(parallel_count, 3);
+        assert_eq!(reported, 3);
+        assert_eq!(read_tree(&serial), read_tree(&parallel));
+
+        fs::remove_dir_all(&serial).unwrap();
+        fs::remove_dir_all(&parallel).unwrap();
+    }
+
+    #[test]
+    fn skipped_progress() {
+        let arc = labeled_test_arc();
+        let dest = blocked_test_dest("parallel-skip");
+
+        let mut last = (0, 0);
+        let extracted = arc
+            .extract_dir_parallel(
+                "/",
+                &dest,
+                Region::UsEnglish,
+                ExtractOptions {
+                    progress: Some(&mut |progress: ExtractProgress| {
+                        assert_eq!(progress.total, 3);
+                        last = (progress.extracted, progress.skipped);
+                    }),
+                    skip_errors: true,
+                },
+                || Ok(Cursor::new(test_arc_bytes())),
+            )
+            .unwrap();
+
+        assert_eq!(extracted, 1);
+        assert_eq!(last, (1, 2));
+
+        fs::remove_dir_all(&dest).unwrap();
+    }
+
+    #[test]
+    fn reader_errors() {
+        let arc = labeled_test_arc();
+        let dest = test_dest("reader-errors");
+
+        let result = arc.extract_dir_parallel(
+            "/",
+            &dest,
+            Region::UsEnglish,
+            Default::default(),
+            || -> io::Result<Cursor<Vec<u8>>> { Err(io::ErrorKind::NotFound.into()) },
+        );
+
+        assert!(matches!(result, Err(LookupError::FileRead(_))));
+
+        let _ = fs::remove_dir_all(&dest);
+    }
+}
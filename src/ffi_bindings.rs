@@ -17,7 +17,8 @@ pub unsafe extern "C" fn arc_open(path: *const i8) -> Option<Box<ArcFile>> {
 }
 
 /// Open an ArcFile from a given IP address.
-/// This is intended for use with [arc-network](https://github.com/jam1garner/arc-network).
+/// The server must listen on port 43022 and speak the protocol described in
+/// [`NetworkReader`](crate::NetworkReader).
 ///
 /// **Note:** `Box<ArcFile>` is equivelant in layout to `*mut ArcFile`, but should be treated
 /// as an opaque pointer
@@ -30,7 +31,7 @@ pub unsafe extern "C" fn arc_open_networked(ip: *const i8) -> Option<Box<ArcFile
     let ip = ip.to_string_lossy().into_owned();
 
     Some(Box::new(
-        ArcFile::open_over_network((ip.as_str(), 43022)).ok()?,
+        ArcFile::open_over_network((ip.as_str(), crate::DEFAULT_PORT)).ok()?,
     ))
 }
 
@@ -183,7 +184,7 @@ pub unsafe extern "C" fn arc_hash40_to_str(hash: Hash40) -> *mut i8 {
 #[no_mangle]
 pub unsafe extern "C" fn arc_str_to_hash40(string: *const i8) -> Hash40 {
     let string = std::ffi::CStr::from_ptr(string);
-    hash40::hash40_from_bytes(string.to_bytes())
+    Hash40::from_bytes(string.to_bytes())
 }
 
 /// Frees the memory allocated by [arc_hash40_to_str].
@@ -0,0 +1,220 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::zstd_backend::Decoder;
+use crate::ReadAt;
+
+#[cfg(doc)]
+use crate::ArcLookup;
+
+/// A reader over the contents of a single file, returned by [`ArcLookup::open_file`].
+/// Compressed files are decompressed as they are read rather than all at once.
+///
+/// Stream files and uncompressed files can also be seeked within. Seeking within a compressed
+/// file returns an error of kind [`io::ErrorKind::Unsupported`].
+pub struct FileReader<'a> {
+    inner: Inner<'a>,
+    len: u64,
+}
+
+enum Inner<'a> {
+    Raw(RangeReader<'a>),
+    Compressed(Decoder<RangeReader<'a>>),
+}
+
+impl<'a> FileReader<'a> {
+    pub(crate) fn raw(reader: Box<dyn ReadAt + 'a>, offset: u64, size: u64) -> Self {
+        Self {
+            inner: Inner::Raw(RangeReader::new(reader, offset, size)),
+            len: size,
+        }
+    }
+
+    pub(crate) fn compressed(
+        reader: Box<dyn ReadAt + 'a>,
+        offset: u64,
+        comp_size: u64,
+        decomp_size: u64,
+    ) -> io::Result<Self> {
+        let decoder = Decoder::new(RangeReader::new(reader, offset, comp_size))?;
+
+        Ok(Self {
+            inner: Inner::Compressed(decoder),
+            len: decomp_size,
+        })
+    }
+
+    /// The size of the file's contents once decompressed
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether the file is decompressed while reading, in which case it can't be seeked within
+    pub fn is_compressed(&self) -> bool {
+        matches!(self.inner, Inner::Compressed(_))
+    }
+}
+
+impl Read for FileReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.inner {
+            Inner::Raw(reader) => reader.read(buf),
+            Inner::Compressed(decoder) => decoder.read(buf),
+        }
+    }
+}
+
+impl Seek for FileReader<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match &mut self.inner {
+            Inner::Raw(reader) => reader.seek(pos),
+            Inner::Compressed(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "compressed files can't be seeked within",
+            )),
+        }
+    }
+}
+
+/// Reads `len` bytes of a [`ReadAt`] starting from `start`
+struct RangeReader<'a> {
+    reader: Box<dyn ReadAt + 'a>,
+    start: u64,
+    len: u64,
+    pos: u64,
+}
+
+impl<'a> RangeReader<'a> {
+    fn new(reader: Box<dyn ReadAt + 'a>, start: u64, len: u64) -> Self {
+        Self {
+            reader,
+            start,
+            len,
+            pos: 0,
+        }
+    }
+}
+
+impl Read for RangeReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.len.saturating_sub(self.pos);
+        let len = remaining.min(buf.len() as u64) as usize;
+        if len == 0 {
+            return Ok(0);
+        }
+
+        let read = self
+            .reader
+            .read_at(&mut buf[..len], self.start + self.pos)?;
+        if read == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the arc ended before the end of the file",
+            ));
+        }
+        self.pos += read as u64;
+
+        Ok(read)
+    }
+}
+
+impl Seek for RangeReader<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+        };
+
+        self.pos = new_pos.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+
+        Ok(self.pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arc_file::tests::{test_arc, TEST_FILE_CONTENTS, TEST_STREAM_CONTENTS};
+    use crate::{ArcLookup, Compression, Region, SharedFileMode};
+
+    const MODEL: &str = "fighter/mario/model.numdlb";
+    const STREAM: &str = "stream:/sound/bgm/bgm_test.nus3audio";
+
+    #[test]
+    fn read_stream_file() {
+        let arc = test_arc();
+        let mut reader = arc.open_file(STREAM, Region::UsEnglish).unwrap();
+        assert!(!reader.is_compressed());
+        assert_eq!(reader.len(), TEST_STREAM_CONTENTS.len() as u64);
+
+        let mut contents = Vec::new();
+        reader.read_to_end(&mut contents).unwrap();
+        assert_eq!(contents, TEST_STREAM_CONTENTS);
+
+        let mut end = [0; 4];
+        reader.seek(SeekFrom::End(-4)).unwrap();
+        reader.read_exact(&mut end).unwrap();
+        assert_eq!(end, TEST_STREAM_CONTENTS[0xC..]);
+        assert_eq!(reader.read(&mut end).unwrap(), 0);
+    }
+
+    #[test]
+    fn read_uncompressed_file() {
+        let arc = test_arc();
+        let mut reader = arc.open_file(MODEL, Region::UsEnglish).unwrap();
+
+        let mut contents = Vec::new();
+        reader.read_to_end(&mut contents).unwrap();
+        assert_eq!(contents, TEST_FILE_CONTENTS);
+        assert!(reader.seek(SeekFrom::Start(6)).is_ok());
+    }
+
+    #[test]
+    fn read_compressed_file() {
+        let data = (0..0x10000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+
+        let mut arc = test_arc();
+        arc.replace_file(
+            MODEL,
+            Region::UsEnglish,
+            &data,
+            Compression::Zstd,
+            SharedFileMode::ReplaceAll,
+        )
+        .unwrap();
+
+        let mut reader = arc.open_file(MODEL, Region::UsEnglish).unwrap();
+        assert!(reader.is_compressed());
+        assert_eq!(reader.len(), data.len() as u64);
+        assert!(reader.seek(SeekFrom::Start(0)).is_err());
+
+        let mut contents = Vec::new();
+        let mut chunk = [0; 0x1000];
+        loop {
+            match reader.read(&mut chunk).unwrap() {
+                0 => break,
+                read => contents.extend_from_slice(&chunk[..read]),
+            }
+        }
+        assert_eq!(contents, data);
+    }
+
+    #[test]
+    fn missing_file() {
+        let arc = test_arc();
+
+        assert!(matches!(
+            arc.open_file("fighter/luigi/model.numdlb", Region::UsEnglish),
+            Err(crate::LookupError::Missing)
+        ));
+    }
+}
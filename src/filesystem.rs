@@ -2,10 +2,10 @@ use crate::{FileDataIdx, FileInfoIdx, FileInfoIndiceIdx, FilePathIdx, Hash40, In
 use modular_bitfield::prelude::*;
 use std::convert::TryFrom;
 
-use binrw::{binread, io::*, BinRead, BinResult, Endian};
+use binrw::{binrw, io::*, BinRead, BinResult, BinWrite, Endian};
 
-#[derive(BinRead, Debug, Clone, Copy)]
-#[br(magic = 0x10_u32)]
+#[derive(BinRead, BinWrite, Debug, Clone, Copy)]
+#[brw(magic = 0x10_u32)]
 pub struct CompTableHeader {
     pub decomp_size: u32,
     pub comp_size: u32,
@@ -59,17 +59,87 @@ impl BinRead for CompressedSearchFileSystem {
         SearchFileSystem::read_options(&mut decompressed, endian, ())
             .map(CompressedSearchFileSystem)
     }
+}
+
+/// Alignment of the end of a compressed table section, relative to the start of its header
+const COMP_TABLE_ALIGNMENT: u32 = 0x10;
+
+/// Compress a serialized table and write it out as a [`CompTableHeader`]-prefixed section, the
+/// inverse of how [`CompressedFileSystem`] and [`CompressedSearchFileSystem`] are read.
+///
+/// Both tables start with their own size (`size_len` bytes), which is set from the serialized
+/// table first so the header can't disagree with the table after it has been edited.
+fn write_comp_table<W>(
+    writer: &mut W,
+    endian: Endian,
+    mut decompressed: Vec<u8>,
+    size_len: usize,
+) -> BinResult<()>
+where
+    W: Write + Seek,
+{
+    let size = decompressed.len() as u64;
+    match endian {
+        Endian::Little => decompressed[..size_len].copy_from_slice(&size.to_le_bytes()[..size_len]),
+        Endian::Big => {
+            decompressed[..size_len].copy_from_slice(&size.to_be_bytes()[8 - size_len..])
+        }
+    }
+
+    let decompressed = &decompressed[..];
+    let compressed = crate::zstd_backend::encode_all(decompressed)?;
+
+    let header_size = 0x10;
+    let unpadded_size = header_size + compressed.len() as u32;
+    let section_size = (unpadded_size + COMP_TABLE_ALIGNMENT - 1) & !(COMP_TABLE_ALIGNMENT - 1);
+
+    let header = CompTableHeader {
+        decomp_size: decompressed.len() as u32,
+        comp_size: compressed.len() as u32,
+        section_size,
+    };
+
+    header.write_options(writer, endian, ())?;
+    writer.write_all(&compressed)?;
+    writer.write_all(&vec![0; (section_size - unpadded_size) as usize])?;
 
+    Ok(())
+}
+
+impl FileSystem {
+    /// Serialize, zstd-compress and write the filesystem as it is stored in the data.arc,
+    /// including the [`CompTableHeader`] and section padding. The written
+    /// [`FileSystemHeader::table_filesize`] is the size of the serialized table, whatever the
+    /// header currently holds.
+    pub fn write_compressed<W: Write + Seek>(&self, writer: &mut W) -> BinResult<()> {
+        let mut decompressed = Cursor::new(Vec::new());
+        self.write_options(&mut decompressed, Endian::Little, ())?;
+
+        write_comp_table(writer, Endian::Little, decompressed.into_inner(), 4)
+    }
+}
+
+impl SearchFileSystem {
+    /// Serialize, zstd-compress and write the search filesystem as it is stored in the data.arc,
+    /// including the [`CompTableHeader`] and section padding. The written
+    /// [`SearchFileSystemHeader::size`] is the size of the serialized table, whatever the header
+    /// currently holds.
+    pub fn write_compressed<W: Write + Seek>(&self, writer: &mut W) -> BinResult<()> {
+        let mut decompressed = Cursor::new(Vec::new());
+        self.write_options(&mut decompressed, Endian::Little, ())?;
+
+        write_comp_table(writer, Endian::Little, decompressed.into_inner(), 8)
+    }
 }
 
 /// The filesystem itself. Includes all the linking between paths, file data, directories, and
 /// mass-loading groups.
-#[binread]
+#[binrw]
 #[derive(Debug)]
 pub struct FileSystem {
     pub fs_header: FileSystemHeader,
 
-    #[br(align_before = 0x100)]
+    #[brw(align_before = 0x100)]
     pub stream_header: StreamHeader,
 
     #[br(count = stream_header.quick_dir_count)]
@@ -88,9 +158,11 @@ pub struct FileSystem {
     pub stream_datas: Vec<StreamData>,
 
     #[br(temp)]
+    #[bw(calc = file_hash_to_path_index.len() as u32)]
     pub hash_index_group_count: u32,
 
     #[br(temp)]
+    #[bw(calc = file_info_buckets.len() as u32)]
     pub bucket_count: u32,
 
     #[br(count = bucket_count)]
@@ -112,22 +184,38 @@ pub struct FileSystem {
     pub dir_infos: Vec<DirInfo>,
 
     #[br(count = fs_header.folder_offset_count_1 + fs_header.folder_offset_count_2 + fs_header.extra_folder)]
+    #[bw(assert(
+        folder_offsets.len() == (fs_header.folder_offset_count_1 + fs_header.folder_offset_count_2 + fs_header.extra_folder) as usize,
+        "folder offset count does not match the filesystem header"
+    ))]
     pub folder_offsets: Vec<DirectoryOffset>,
 
     #[br(count = fs_header.hash_folder_count)]
     pub folder_child_hashes: Vec<HashToIndex>,
 
     #[br(count = fs_header.file_info_count + fs_header.file_data_count_2 + fs_header.extra_count)]
+    #[bw(assert(
+        file_infos.len() == (fs_header.file_info_count + fs_header.file_data_count_2 + fs_header.extra_count) as usize,
+        "file info count does not match the filesystem header"
+    ))]
     pub file_infos: Vec<FileInfo>,
 
     #[br(count = fs_header.file_info_sub_index_count + fs_header.file_data_count_2 + fs_header.extra_count_2)]
+    #[bw(assert(
+        file_info_to_datas.len() == (fs_header.file_info_sub_index_count + fs_header.file_data_count_2 + fs_header.extra_count_2) as usize,
+        "file info to data count does not match the filesystem header"
+    ))]
     pub file_info_to_datas: Vec<FileInfoToFileData>,
 
     #[br(count = fs_header.file_data_count + fs_header.file_data_count_2 + fs_header.extra_sub_count)]
+    #[bw(assert(
+        file_datas.len() == (fs_header.file_data_count + fs_header.file_data_count_2 + fs_header.extra_sub_count) as usize,
+        "file data count does not match the filesystem header"
+    ))]
     pub file_datas: Vec<FileData>,
 }
 
-#[binread]
+#[binrw]
 #[derive(Debug)]
 pub struct SearchFileSystem {
     pub header: SearchFileSystemHeader,
@@ -149,8 +237,9 @@ pub struct SearchFileSystem {
 }
 
 #[repr(C)]
-#[derive(BinRead, Debug, Clone, Copy)]
+#[derive(BinRead, BinWrite, Debug, Clone, Copy)]
 pub struct FileSystemHeader {
+    /// The size of the decompressed table, see [`FileSystem::write_compressed`]
     pub table_filesize: u32,
     pub file_info_path_count: u32,
     pub file_info_index_count: u32,
@@ -185,8 +274,9 @@ pub struct FileSystemHeader {
 }
 
 #[repr(C)]
-#[derive(BinRead, Debug, Copy, Clone)]
+#[derive(BinRead, BinWrite, Debug, Copy, Clone)]
 pub struct SearchFileSystemHeader {
+    /// The size of the decompressed table, see [`SearchFileSystem::write_compressed`]
     pub size: u64,
     pub folder_count: u32,
     pub path_index_count: u32,
@@ -194,7 +284,7 @@ pub struct SearchFileSystemHeader {
 }
 
 #[repr(C)]
-#[derive(BinRead, Debug)]
+#[derive(BinRead, BinWrite, Debug)]
 pub struct StreamHeader {
     pub quick_dir_count: u32,
     pub stream_hash_count: u32,
@@ -203,8 +293,9 @@ pub struct StreamHeader {
 }
 
 #[bitfield]
-#[derive(BinRead, Debug, Clone, Copy)]
+#[derive(BinRead, BinWrite, Debug, Clone, Copy)]
 #[br(map = Self::from_bytes)]
+#[bw(map = |x: &Self| x.into_bytes())]
 pub struct QuickDir {
     pub hash: u32,
     pub name_length: u8,
@@ -213,15 +304,16 @@ pub struct QuickDir {
 }
 
 #[repr(C)]
-#[derive(BinRead, Debug, Clone, Copy)]
+#[derive(BinRead, BinWrite, Debug, Clone, Copy)]
 pub struct StreamEntry {
     pub path: HashToIndex,
     pub flags: StreamEntryFlags,
 }
 
 #[bitfield]
-#[derive(BinRead, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(BinRead, BinWrite, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[br(map = Self::from_bytes)]
+#[bw(map = |x: &Self| x.into_bytes())]
 pub struct StreamEntryFlags {
     pub is_regional: bool,
     pub is_localized: bool,
@@ -229,8 +321,9 @@ pub struct StreamEntryFlags {
 }
 
 #[bitfield]
-#[derive(BinRead, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[derive(BinRead, BinWrite, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 #[br(map = Self::from_bytes)]
+#[bw(map = |x: &Self| x.into_bytes())]
 pub struct HashToIndex {
     pub hash: u32,
     pub length: u8,
@@ -244,13 +337,13 @@ impl PartialEq<Hash40> for HashToIndex {
 }
 
 #[repr(C)]
-#[derive(BinRead, Debug, Clone, Copy)]
+#[derive(BinRead, BinWrite, Debug, Clone, Copy)]
 pub struct FileInfoBucket {
     pub start: u32,
     pub count: u32,
 }
 #[repr(C)]
-#[derive(BinRead, Debug, Clone, Copy)]
+#[derive(BinRead, BinWrite, Debug, Clone, Copy)]
 pub struct FilePath {
     pub path: HashToIndex,
     pub ext: HashToIndex,
@@ -258,7 +351,7 @@ pub struct FilePath {
     pub file_name: HashToIndex,
 }
 #[repr(C)]
-#[derive(BinRead, Debug, Clone, Copy)]
+#[derive(BinRead, BinWrite, Debug, Clone, Copy)]
 pub struct FileInfoIndex {
     pub dir_offset_index: u32,
     pub file_info_index: FileInfoIdx,
@@ -267,7 +360,8 @@ pub struct FileInfoIndex {
 /// Also known as MassLoadingGroup
 #[repr(C)]
 #[cfg_attr(feature = "smash-runtime", repr(packed))]
-#[derive(BinRead, Debug, Clone, Copy)]
+#[derive(BinRead, BinWrite, Debug, Clone, Copy)]
+#[bw(map = Self::fields)]
 pub struct DirInfo {
     pub path: HashToIndex,
     pub name: Hash40,
@@ -281,6 +375,27 @@ pub struct DirInfo {
     pub flags: DirInfoFlags,
 }
 
+impl DirInfo {
+    // Fields are copied out rather than borrowed as the struct is packed under smash-runtime
+    fn fields(&self) -> (HashToIndex, Hash40, Hash40, [u32; 6], DirInfoFlags) {
+        let dir = *self;
+        (
+            dir.path,
+            dir.name,
+            dir.parent,
+            [
+                dir.extra_dis_re,
+                dir.extra_dis_re_length,
+                dir.file_info_start_index,
+                dir.file_count,
+                dir.child_dir_start_index,
+                dir.child_dir_count,
+            ],
+            dir.flags,
+        )
+    }
+}
+
 #[repr(C)]
 #[derive(Debug, Clone)]
 pub enum RedirectionType {
@@ -289,8 +404,9 @@ pub enum RedirectionType {
 }
 
 #[bitfield]
-#[derive(BinRead, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(BinRead, BinWrite, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[br(map = Self::from_bytes)]
+#[bw(map = |x: &Self| x.into_bytes())]
 pub struct DirInfoFlags {
     pub unk1: B26,
     pub redirected: bool,
@@ -300,7 +416,7 @@ pub struct DirInfoFlags {
 }
 
 #[repr(C)]
-#[derive(BinRead, Debug, Clone, Copy)]
+#[derive(BinRead, BinWrite, Debug, Clone, Copy)]
 pub struct StreamData {
     pub size: u64,
     pub offset: u64,
@@ -308,7 +424,8 @@ pub struct StreamData {
 
 /// Also known as MassLoadingData
 #[repr(packed)]
-#[derive(BinRead, Debug, Clone, Copy)]
+#[derive(BinRead, BinWrite, Debug, Clone, Copy)]
+#[bw(map = Self::fields)]
 pub struct DirectoryOffset {
     pub offset: u64,
     pub decomp_size: u32,
@@ -320,8 +437,25 @@ pub struct DirectoryOffset {
     pub directory_index: u32,
 }
 
+impl DirectoryOffset {
+    // Fields are copied out rather than borrowed as the struct is packed
+    fn fields(&self) -> (u64, [u32; 5]) {
+        let offset = *self;
+        (
+            offset.offset,
+            [
+                offset.decomp_size,
+                offset.size,
+                offset.file_start_index,
+                offset.file_count,
+                offset.directory_index,
+            ],
+        )
+    }
+}
+
 #[repr(C)]
-#[derive(BinRead, Debug, Clone, Copy)]
+#[derive(BinRead, BinWrite, Debug, Clone, Copy)]
 pub struct FileInfo {
     pub file_path_index: FilePathIdx,
     pub file_info_indice_index: FileInfoIndiceIdx,
@@ -332,8 +466,9 @@ pub struct FileInfo {
 }
 
 #[bitfield]
-#[derive(BinRead, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(BinRead, BinWrite, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[br(map = Self::from_bytes)]
+#[bw(map = |x: &Self| x.into_bytes())]
 pub struct FileInfoFlags {
     pub unused: B4,
     pub is_redirect: bool,
@@ -349,7 +484,7 @@ pub struct FileInfoFlags {
 }
 
 #[repr(C)]
-#[derive(BinRead, Debug, Clone, Copy)]
+#[derive(BinRead, BinWrite, Debug, Clone, Copy)]
 pub struct FileInfoToFileData {
     pub folder_offset_index: u32,
     pub file_data_index: FileDataIdx,
@@ -357,15 +492,16 @@ pub struct FileInfoToFileData {
 }
 
 #[bitfield]
-#[derive(BinRead, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(BinRead, BinWrite, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[br(map = Self::from_bytes)]
+#[bw(map = |x: &Self| x.into_bytes())]
 pub struct FileInfoToFileDataBitfield {
     pub file_info_idx: B24,
     pub load_type: u8,
 }
 
 #[repr(C)]
-#[derive(BinRead, Debug, Clone, Copy)]
+#[derive(BinRead, BinWrite, Debug, Clone, Copy)]
 pub struct FileData {
     pub offset_in_folder: u32,
     pub comp_size: u32,
@@ -374,8 +510,9 @@ pub struct FileData {
 }
 
 #[bitfield]
-#[derive(BinRead, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(BinRead, BinWrite, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[br(map = Self::from_bytes)]
+#[bw(map = |x: &Self| x.into_bytes())]
 pub struct FileDataFlags {
     pub compressed: bool,
     pub use_zstd: bool,
@@ -383,7 +520,7 @@ pub struct FileDataFlags {
 }
 
 #[repr(C)]
-#[derive(BinRead, Debug, Copy, Clone)]
+#[derive(BinRead, BinWrite, Debug, Copy, Clone)]
 pub struct SearchListEntry {
     pub path: HashToIndex,
     pub parent: HashToIndex,
@@ -392,11 +529,11 @@ pub struct SearchListEntry {
 }
 
 #[repr(transparent)]
-#[derive(BinRead, Debug, Copy, Clone)]
+#[derive(BinRead, BinWrite, Debug, Copy, Clone)]
 pub struct PathListEntry(pub SearchListEntry);
 
 #[repr(transparent)]
-#[derive(BinRead, Debug, Copy, Clone)]
+#[derive(BinRead, BinWrite, Debug, Copy, Clone)]
 pub struct FolderPathListEntry(pub SearchListEntry);
 
 macro_rules! impl_fs_index {
@@ -506,3 +643,316 @@ impl FolderPathListEntry {
         self.ext.set_hash(idx & 0xFF_FFFF)
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::hash40::hash40;
+
+    pub(crate) fn hash_to_index(path: &str, index: u32) -> HashToIndex {
+        let hash = hash40(path);
+
+        HashToIndex::new()
+            .with_hash(hash.crc32())
+            .with_length(hash.len())
+            .with_index(index)
+    }
+
+    fn search_entry(path: &str, parent: &str, file_name: &str, ext: &str) -> SearchListEntry {
+        SearchListEntry {
+            path: hash_to_index(path, 0xFF_FFFF),
+            parent: hash_to_index(parent, 0),
+            file_name: hash_to_index(file_name, 0),
+            ext: hash_to_index(ext, 0),
+        }
+    }
+
+    /// A minimal filesystem containing two files in `fighter/mario`, `model.numdlb` and
+    /// `alias.numdlb`, which share the same uncompressed data at the start of the file section,
+    /// and a single stream file.
+    pub(crate) fn test_file_system() -> FileSystem {
+        let mut file_hash_to_path_index = vec![
+            hash_to_index("fighter/mario/model.numdlb", 0),
+            hash_to_index("fighter/mario/alias.numdlb", 1),
+        ];
+        file_hash_to_path_index.sort_by_key(HashToIndex::hash40);
+
+        FileSystem {
+            fs_header: FileSystemHeader {
+                table_filesize: 0x254,
+                file_info_path_count: 2,
+                file_info_index_count: 2,
+                folder_count: 1,
+                folder_offset_count_1: 1,
+                hash_folder_count: 0,
+                file_info_count: 2,
+                file_info_sub_index_count: 2,
+                file_data_count: 1,
+                folder_offset_count_2: 0,
+                file_data_count_2: 0,
+                padding: 0,
+                unk1_10: 0x10,
+                unk2_10: 0x10,
+                regional_count_1: 14,
+                regional_count_2: 6,
+                padding2: 0,
+                version: 0x000C_0000,
+                extra_folder: 0,
+                extra_count: 0,
+                unk: [0; 2],
+                extra_count_2: 0,
+                extra_sub_count: 0,
+            },
+            stream_header: StreamHeader {
+                quick_dir_count: 1,
+                stream_hash_count: 1,
+                stream_file_index_count: 1,
+                stream_offset_entry_count: 1,
+            },
+            quick_dirs: vec![QuickDir::new()
+                .with_hash(hash40("bgm").crc32())
+                .with_name_length(3)
+                .with_count(1)
+                .with_index(0)],
+            stream_hash_to_entries: vec![hash_to_index("stream:/sound/bgm/bgm_test.nus3audio", 0)],
+            stream_entries: vec![StreamEntry {
+                path: hash_to_index("stream:/sound/bgm/bgm_test.nus3audio", 0),
+                flags: StreamEntryFlags::new(),
+            }],
+            stream_file_indices: vec![0],
+            stream_datas: vec![StreamData {
+                size: 0x10,
+                offset: 0x100,
+            }],
+            file_info_buckets: vec![FileInfoBucket { start: 0, count: 2 }],
+            file_hash_to_path_index,
+            file_paths: vec![
+                FilePath {
+                    path: hash_to_index("fighter/mario/model.numdlb", 0),
+                    ext: hash_to_index("numdlb", 0),
+                    parent: hash_to_index("fighter/mario", 0),
+                    file_name: hash_to_index("model.numdlb", 0),
+                },
+                FilePath {
+                    path: hash_to_index("fighter/mario/alias.numdlb", 1),
+                    ext: hash_to_index("numdlb", 0),
+                    parent: hash_to_index("fighter/mario", 0),
+                    file_name: hash_to_index("alias.numdlb", 0),
+                },
+            ],
+            file_info_indices: vec![
+                FileInfoIndex {
+                    dir_offset_index: 0,
+                    file_info_index: FileInfoIdx(0),
+                },
+                FileInfoIndex {
+                    dir_offset_index: 0,
+                    file_info_index: FileInfoIdx(1),
+                },
+            ],
+            dir_hash_to_info_index: vec![hash_to_index("fighter/mario", 0)],
+            dir_infos: vec![DirInfo {
+                path: hash_to_index("fighter/mario", 0),
+                name: hash40("mario"),
+                parent: hash40("fighter"),
+                extra_dis_re: 0,
+                extra_dis_re_length: 0,
+                file_info_start_index: 0,
+                file_count: 2,
+                child_dir_start_index: 0,
+                child_dir_count: 0,
+                flags: DirInfoFlags::new(),
+            }],
+            folder_offsets: vec![DirectoryOffset {
+                offset: 0,
+                decomp_size: 0x10,
+                size: 0x10,
+                file_start_index: 0,
+                file_count: 2,
+                directory_index: 0xFF_FFFF,
+            }],
+            folder_child_hashes: vec![],
+            file_infos: (0..2)
+                .map(|index| FileInfo {
+                    file_path_index: FilePathIdx(index),
+                    file_info_indice_index: FileInfoIndiceIdx(index),
+                    info_to_data_index: InfoToDataIdx(index),
+                    flags: FileInfoFlags::new(),
+                })
+                .collect(),
+            file_info_to_datas: (0..2)
+                .map(|index| FileInfoToFileData {
+                    folder_offset_index: 0,
+                    file_data_index: FileDataIdx(0),
+                    file_info_index_and_load_type: FileInfoToFileDataBitfield::new()
+                        .with_file_info_idx(index)
+                        .with_load_type(1),
+                })
+                .collect(),
+            file_datas: vec![FileData {
+                offset_in_folder: 0,
+                comp_size: 0x10,
+                decomp_size: 0x10,
+                flags: FileDataFlags::new(),
+            }],
+        }
+    }
+
+    /// The search counterpart of [`test_file_system`].
+    pub(crate) fn test_search_file_system() -> SearchFileSystem {
+        let mut folder = search_entry("fighter/mario", "fighter", "mario", "");
+        folder.parent.set_index(2);
+        folder.ext = HashToIndex::new().with_hash(0);
+
+        let mut model = search_entry(
+            "fighter/mario/model.numdlb",
+            "fighter/mario",
+            "model.numdlb",
+            "numdlb",
+        );
+        model.path.set_index(1);
+        let alias = search_entry(
+            "fighter/mario/alias.numdlb",
+            "fighter/mario",
+            "alias.numdlb",
+            "numdlb",
+        );
+
+        let mut path_index_lookup = vec![
+            hash_to_index("fighter/mario/model.numdlb", 0),
+            hash_to_index("fighter/mario/alias.numdlb", 1),
+        ];
+        path_index_lookup.sort_by_key(HashToIndex::hash40);
+
+        SearchFileSystem {
+            header: SearchFileSystemHeader {
+                size: 0x94,
+                folder_count: 1,
+                path_index_count: 2,
+                path_count: 2,
+            },
+            folder_lookup: vec![hash_to_index("fighter/mario", 0)],
+            folders: vec![FolderPathListEntry(folder)],
+            path_index_lookup,
+            path_indices: vec![0, 1],
+            paths: vec![PathListEntry(model), PathListEntry(alias)],
+        }
+    }
+
+    fn to_bytes<T>(value: &T) -> Vec<u8>
+    where
+        T: BinWrite,
+        for<'a> T::Args<'a>: Default,
+    {
+        let mut writer = Cursor::new(Vec::new());
+        value
+            .write_options(&mut writer, Endian::Little, Default::default())
+            .unwrap();
+
+        writer.into_inner()
+    }
+
+    #[test]
+    fn file_system_round_trip() {
+        let bytes = to_bytes(&test_file_system());
+
+        // The stream header is aligned to 0x100 after the filesystem header
+        assert_eq!(bytes[0x58..0x100], [0; 0xA8]);
+        assert_eq!(bytes[0x100..0x104], 1u32.to_le_bytes());
+
+        let fs = FileSystem::read_options(&mut Cursor::new(&bytes), Endian::Little, ()).unwrap();
+
+        assert_eq!(fs.file_info_buckets.len(), 1);
+        assert_eq!(fs.file_hash_to_path_index.len(), 2);
+        assert_eq!({ fs.folder_offsets[0].directory_index }, 0xFF_FFFF);
+        assert_eq!(bytes, to_bytes(&fs));
+    }
+
+    #[test]
+    fn search_file_system_round_trip() {
+        let bytes = to_bytes(&test_search_file_system());
+        let search =
+            SearchFileSystem::read_options(&mut Cursor::new(&bytes), Endian::Little, ()).unwrap();
+
+        assert_eq!(
+            search.paths[0].path.hash40(),
+            hash40("fighter/mario/model.numdlb")
+        );
+        assert_eq!(bytes, to_bytes(&search));
+    }
+
+    #[test]
+    fn compressed_file_system_round_trip() {
+        let fs = test_file_system();
+
+        let mut writer = Cursor::new(Vec::new());
+        writer.write_all(&[0xFF; 4]).unwrap();
+        fs.write_compressed(&mut writer).unwrap();
+        let end = writer.position();
+
+        let mut reader = Cursor::new(writer.into_inner());
+        reader.set_position(4);
+        let header = CompTableHeader::read_options(&mut reader, Endian::Little, ()).unwrap();
+
+        assert_eq!(header.decomp_size as usize, to_bytes(&fs).len());
+        assert_eq!(header.section_size as u64, end - 4);
+        assert_eq!(header.section_size % COMP_TABLE_ALIGNMENT, 0);
+
+        reader.set_position(4);
+        let read = CompressedFileSystem::read_options(&mut reader, Endian::Little, ()).unwrap();
+
+        assert_eq!(reader.position(), end);
+        assert_eq!(to_bytes(&fs), to_bytes(&read.0));
+    }
+
+    #[test]
+    fn compressed_search_file_system_round_trip() {
+        let search = test_search_file_system();
+
+        let mut writer = Cursor::new(Vec::new());
+        search.write_compressed(&mut writer).unwrap();
+
+        writer.set_position(0);
+        let read =
+            CompressedSearchFileSystem::read_options(&mut writer, Endian::Little, ()).unwrap();
+
+        assert_eq!(to_bytes(&search), to_bytes(&read.0));
+    }
+
+    #[test]
+    fn write_compressed_table_sizes() {
+        let mut fs = test_file_system();
+        let mut search = test_search_file_system();
+        assert_eq!(fs.fs_header.table_filesize as usize, to_bytes(&fs).len());
+        assert_eq!({ search.header.size } as usize, to_bytes(&search).len());
+
+        // Stale sizes are replaced by the size of the written table
+        fs.fs_header.table_filesize = 0;
+        search.header.size = 0;
+
+        let mut writer = Cursor::new(Vec::new());
+        fs.write_compressed(&mut writer).unwrap();
+        writer.set_position(0);
+        let read = CompressedFileSystem::read_options(&mut writer, Endian::Little, ()).unwrap();
+        assert_eq!(
+            read.0.fs_header.table_filesize as usize,
+            to_bytes(&read.0).len()
+        );
+
+        let mut writer = Cursor::new(Vec::new());
+        search.write_compressed(&mut writer).unwrap();
+        writer.set_position(0);
+        let read =
+            CompressedSearchFileSystem::read_options(&mut writer, Endian::Little, ()).unwrap();
+        assert_eq!({ read.0.header.size } as usize, to_bytes(&read.0).len());
+    }
+
+    #[test]
+    fn file_system_mismatched_counts() {
+        let mut fs = test_file_system();
+        fs.file_datas.push(fs.file_datas[0]);
+
+        let mut writer = Cursor::new(Vec::new());
+        assert!(fs.write_options(&mut writer, Endian::Little, ()).is_err());
+    }
+}
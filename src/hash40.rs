@@ -1,9 +1,13 @@
+use std::fmt;
+use std::str::FromStr;
+
 use crate::{HashToIndex, QuickDir};
-use binrw::BinRead;
+use binrw::{BinRead, BinWrite};
 use crc32fast::Hasher;
+use thiserror::Error;
 
 #[repr(transparent)]
-#[derive(BinRead, Debug, Clone, Copy, PartialEq, Eq, Ord, PartialOrd, Hash)]
+#[derive(BinRead, BinWrite, Debug, Clone, Copy, PartialEq, Eq, Ord, PartialOrd, Hash)]
 pub struct Hash40(pub u64);
 
 impl Hash40 {
@@ -18,6 +22,115 @@ impl Hash40 {
     pub fn crc32(self) -> u32 {
         self.0 as u32
     }
+
+    /// The hash of a string given as bytes, the same as [`hash40`]
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let mut builder = Hash40Builder::new();
+        builder.update(bytes);
+
+        builder.finish()
+    }
+
+    /// The hash of the label of `self` followed by the label of `other`, without needing either
+    /// label
+    pub fn concat(self, other: Hash40) -> Self {
+        let mut builder = Hash40Builder::from(self);
+        builder.append(&Hash40Builder::from(other));
+
+        builder.finish()
+    }
+}
+
+/// Formats as the hash in hex, such as `0x0a1b2c3d4e`
+impl fmt::Display for Hash40 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#012x}", self.0)
+    }
+}
+
+/// Parses a hash in hex, such as `0x0a1b2c3d4e`. To get the hash of a string use [`hash40`].
+impl FromStr for Hash40 {
+    type Err = ParseHash40Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.strip_prefix("0x")
+            .and_then(|hex| u64::from_str_radix(hex, 16).ok())
+            .filter(|hash| *hash <= 0xFF_FFFF_FFFF)
+            .map(Hash40)
+            .ok_or_else(|| ParseHash40Error(s.to_owned()))
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("invalid hash40 '{0}', expected a 40-bit hex value such as 0x0a1b2c3d4e")]
+pub struct ParseHash40Error(String);
+
+/// Incrementally computes a [`Hash40`], for hashing many strings which share a prefix.
+///
+/// The builder can be cloned after hashing the prefix, and each clone then only needs to hash its
+/// own suffix:
+///
+/// ```
+/// use smash_arc::{hash40, Hash40Builder};
+///
+/// let mut prefix = Hash40Builder::new();
+/// prefix.push_str("fighter/mario/");
+///
+/// for name in ["model", "motion", "sound"] {
+///     let mut builder = prefix.clone();
+///     builder.push_str(name);
+///     assert_eq!(builder.finish(), hash40(&format!("fighter/mario/{}", name)));
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct Hash40Builder {
+    hasher: Hasher,
+    len: u64,
+}
+
+impl Hash40Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.hasher.update(bytes);
+        self.len += bytes.len() as u64;
+    }
+
+    pub fn push_str(&mut self, string: &str) {
+        self.update(string.as_bytes());
+    }
+
+    /// Extend the hashed string with the string hashed by `other`, without rehashing it
+    pub fn append(&mut self, other: &Hash40Builder) {
+        self.hasher.combine(&other.hasher);
+        self.len += other.len;
+    }
+
+    /// The length of the string hashed so far
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The hash of the string hashed so far. The builder can still be extended afterwards.
+    pub fn finish(&self) -> Hash40 {
+        Hash40((self.len << 32) + self.hasher.clone().finalize() as u64)
+    }
+}
+
+/// Continue hashing from an existing hash, as if its label had been hashed
+impl From<Hash40> for Hash40Builder {
+    fn from(hash: Hash40) -> Self {
+        Self {
+            hasher: Hasher::new_with_initial_len(hash.crc32(), hash.len() as u64),
+            len: hash.len() as u64,
+        }
+    }
 }
 
 impl From<&Hash40> for Hash40 {
@@ -54,6 +167,13 @@ impl HashToIndex {
     pub fn hash40(&self) -> Hash40 {
         Hash40((self.hash() as u64) + ((self.length() as u64) << 32))
     }
+
+    pub(crate) fn from_hash40(hash: Hash40, index: u32) -> Self {
+        HashToIndex::new()
+            .with_hash(hash.crc32())
+            .with_length(hash.len())
+            .with_index(index)
+    }
 }
 
 impl QuickDir {
@@ -64,18 +184,7 @@ impl QuickDir {
 
 // Find the hash40 of a given string
 pub fn hash40(string: &str) -> Hash40 {
-    hash40_from_bytes(string.as_bytes())
-}
-
-// TODO: Is this worth adding to the public API?
-pub(crate) fn hash40_from_bytes(bytes: &[u8]) -> Hash40 {
-    Hash40(((bytes.len() as u64) << 32) + crc32(bytes) as u64)
-}
-
-fn crc32(bytes: &[u8]) -> u32 {
-    let mut hasher = Hasher::new();
-    hasher.update(bytes);
-    hasher.finalize()
+    Hash40::from_bytes(string.as_bytes())
 }
 
 #[cfg(feature = "serialize")]
@@ -213,10 +322,7 @@ pub mod serde {
 
 #[cfg(test)]
 mod tests {
-    use crate::{
-        hash40::{hash40, hash40_from_bytes},
-        Hash40,
-    };
+    use crate::{hash40::hash40, Hash40, Hash40Builder};
 
     #[test]
     fn hash40_path_string() {
@@ -230,7 +336,43 @@ mod tests {
     fn hash40_path_bytes() {
         assert_eq!(
             Hash40(0x29954022ed),
-            hash40_from_bytes("fighter/mario/model/body/c00/model.numatb".as_bytes())
+            Hash40::from_bytes("fighter/mario/model/body/c00/model.numatb".as_bytes())
+        );
+    }
+
+    #[test]
+    fn hash40_builder() {
+        let mut builder = Hash40Builder::new();
+        builder.push_str("fighter/mario/");
+        let prefix = builder.clone();
+        assert_eq!(prefix.finish(), hash40("fighter/mario/"));
+
+        builder.push_str("model/body/c00/model.numatb");
+        assert_eq!(builder.finish(), Hash40(0x29954022ed));
+        assert_eq!(builder.len(), 41);
+
+        let mut suffix = Hash40Builder::new();
+        suffix.push_str("model/body/c00/model.numatb");
+        let mut combined = prefix;
+        combined.append(&suffix);
+        assert_eq!(combined.finish(), Hash40(0x29954022ed));
+
+        assert_eq!(
+            hash40("fighter/mario/").concat(hash40("model/body/c00/model.numatb")),
+            Hash40(0x29954022ed)
         );
+        assert_eq!(Hash40Builder::new().finish(), hash40(""));
+    }
+
+    #[test]
+    fn hash40_hex() {
+        let hash = hash40("fighter/mario/model/body/c00/model.numatb");
+        assert_eq!(hash.to_string(), "0x29954022ed");
+        assert_eq!(Hash40(0x1).to_string(), "0x0000000001");
+        assert_eq!("0x29954022ed".parse(), Ok(hash));
+
+        assert!("29954022ed".parse::<Hash40>().is_err());
+        assert!("0xfighter".parse::<Hash40>().is_err());
+        assert!("0x10000000000".parse::<Hash40>().is_err());
     }
 }
@@ -1,30 +1,71 @@
-use std::collections::HashMap;
-use std::fs;
+use std::collections::hash_map::{Entry, HashMap};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
 use std::path::Path;
 
 use crate::{hash40, Hash40};
+#[cfg(feature = "global-hashes")]
 use parking_lot::RwLock;
+use thiserror::Error;
+
+mod binary;
+mod discover;
+mod fill;
+
+pub use binary::BINARY_LABELS_VERSION;
+pub use discover::DiscoverOptions;
 
 pub struct HashLabels {
     pub(crate) labels: HashMap<Hash40, String>,
 }
 
 impl HashLabels {
+    /// Read a label file, either a text file with one label per line or the binary format
+    /// written by [`HashLabels::write_binary`]. If several labels have the same hash the first
+    /// one is kept, see [`HashLabels::from_file_checked`].
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, std::io::Error> {
-        fn inner(path: &Path) -> Result<HashLabels, std::io::Error> {
-            Ok(HashLabels::from_string(&fs::read_to_string(path)?))
+        Self::from_file_checked(path).map(|(labels, _)| labels)
+    }
+
+    /// Read a label file the same as [`HashLabels::from_file`], also returning any labels which
+    /// collided with an earlier label
+    pub fn from_file_checked<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<(Self, Vec<LabelCollision>), std::io::Error> {
+        fn inner(path: &Path) -> Result<(HashLabels, Vec<LabelCollision>), std::io::Error> {
+            let data = fs::read(path)?;
+            if data.starts_with(binary::BINARY_LABELS_MAGIC) {
+                // Binary labels are keyed by hash, so they can't contain collisions
+                return HashLabels::from_binary(&mut io::Cursor::new(data))
+                    .map(|labels| (labels, Vec::new()))
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()));
+            }
+
+            let text = String::from_utf8(data)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+            Ok(HashLabels::from_string_checked(&text))
         }
 
         inner(path.as_ref())
     }
 
+    /// Parse labels from text with one label per line. If several labels have the same hash the
+    /// first one is kept, see [`HashLabels::from_string_checked`].
     pub fn from_string(text: &str) -> Self {
-        HashLabels {
-            labels: text
-                .lines()
-                .map(|line| (hash40(line), line.to_owned()))
-                .collect(),
-        }
+        Self::from_string_checked(text).0
+    }
+
+    /// Parse labels the same as [`HashLabels::from_string`], also returning any labels which
+    /// collided with an earlier label
+    pub fn from_string_checked(text: &str) -> (Self, Vec<LabelCollision>) {
+        let mut labels = HashLabels::new();
+        let collisions = text
+            .lines()
+            .filter_map(|line| labels.insert(line).err())
+            .collect();
+
+        (labels, collisions)
     }
 
     pub(crate) fn add_label<S: Into<String>>(&mut self, label: S) -> Hash40 {
@@ -40,13 +81,93 @@ impl HashLabels {
             labels: Default::default(),
         }
     }
+
+    /// Add a label, returning its hash. If a different label with the same hash is already
+    /// present it is kept and the collision is returned instead.
+    pub fn insert<S: Into<String>>(&mut self, label: S) -> Result<Hash40, LabelCollision> {
+        let label = label.into();
+
+        self.insert_hashed(hash40(&label), label)
+    }
+
+    fn insert_hashed(&mut self, hash: Hash40, label: String) -> Result<Hash40, LabelCollision> {
+        match self.labels.entry(hash) {
+            Entry::Occupied(entry) if *entry.get() != label => Err(LabelCollision {
+                hash,
+                existing: entry.get().clone(),
+                new: label,
+            }),
+            Entry::Occupied(_) => Ok(hash),
+            Entry::Vacant(entry) => {
+                entry.insert(label);
+                Ok(hash)
+            }
+        }
+    }
+
+    /// Add every label from `other`, returning any which collided with an existing label
+    pub fn merge(&mut self, other: &HashLabels) -> Vec<LabelCollision> {
+        other
+            .iter()
+            .filter_map(|(hash, label)| self.insert_hashed(hash, label.to_owned()).err())
+            .collect()
+    }
+
+    /// Iterate over every hash along with its label, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (Hash40, &str)> {
+        self.labels.iter().map(|(hash, label)| (*hash, &**label))
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// Write the labels to a file in the same format read by [`HashLabels::from_file`], one label
+    /// per line in sorted order
+    pub fn write_to<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to_writer(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Write the labels in the same format as [`HashLabels::write_to`]
+    pub fn write_to_writer<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut labels = self.labels.values().collect::<Vec<_>>();
+        labels.sort_unstable();
+        labels.dedup();
+
+        for label in labels {
+            writeln!(writer, "{}", label)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Two different labels with the same hash, returned by [`HashLabels::insert`] and
+/// [`HashLabels::from_string_checked`]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("label {new:?} has the same hash ({hash:?}) as {existing:?}")]
+pub struct LabelCollision {
+    pub hash: Hash40,
+    /// The label which was already present, and was kept
+    pub existing: String,
+    /// The label which was being added
+    pub new: String,
 }
 
 impl Hash40 {
     pub fn label(self, labels: &HashLabels) -> Option<&str> {
         labels.labels.get(&self).map(|x| &**x)
     }
+}
 
+#[cfg(feature = "global-hashes")]
+impl Hash40 {
     pub fn global_label(self) -> Option<String> {
         GLOBAL_LABELS.read().labels.get(&self).map(Clone::clone)
     }
@@ -64,10 +185,12 @@ impl Hash40 {
     }
 
     pub fn set_global_labels(labels: HashLabels) {
-        *GLOBAL_LABELS.write() = labels;
+        let mut global = GLOBAL_LABELS.write();
+        *global = labels;
     }
 }
 
+#[cfg(feature = "global-hashes")]
 lazy_static::lazy_static! {
     pub static ref GLOBAL_LABELS: RwLock<HashLabels> = RwLock::new(HashLabels::new());
 }
@@ -84,6 +207,77 @@ mod tests {
         let matches = labels.get_ordered_matches("mar");
     }
 
+    #[test]
+    fn insert_and_merge() {
+        let mut labels = HashLabels::from_string("fighter\nfighter/mario");
+        assert_eq!(labels.insert("fighter").unwrap(), hash40("fighter"));
+        assert_eq!(labels.len(), 2);
+
+        // Labels with a fake collision, as finding a real crc32 collision of the same length
+        // isn't practical in a test
+        let mut other = HashLabels::new();
+        other.insert("fighter/luigi").unwrap();
+        other
+            .labels
+            .insert(hash40("fighter"), "not_fighter".to_owned());
+
+        let collisions = labels.merge(&other);
+        assert_eq!(
+            collisions,
+            [LabelCollision {
+                hash: hash40("fighter"),
+                existing: "fighter".to_owned(),
+                new: "not_fighter".to_owned(),
+            }]
+        );
+        assert_eq!(hash40("fighter").label(&labels), Some("fighter"));
+        assert_eq!(
+            hash40("fighter/luigi").label(&labels),
+            Some("fighter/luigi")
+        );
+
+        let mut iter = labels.iter().map(|(_, label)| label).collect::<Vec<_>>();
+        iter.sort_unstable();
+        assert_eq!(iter, ["fighter", "fighter/luigi", "fighter/mario"]);
+    }
+
+    #[test]
+    fn from_string_collisions() {
+        // Different labels of the same length with the same crc32
+        assert_eq!(hash40("fighter/l98cu"), hash40("fighter/pvdba"));
+
+        let (labels, collisions) =
+            HashLabels::from_string_checked("fighter/l98cu\nfighter\nfighter/pvdba\nfighter");
+        assert_eq!(
+            collisions,
+            [LabelCollision {
+                hash: hash40("fighter/l98cu"),
+                existing: "fighter/l98cu".to_owned(),
+                new: "fighter/pvdba".to_owned(),
+            }]
+        );
+        assert_eq!(labels.len(), 2);
+        assert_eq!(
+            hash40("fighter/pvdba").label(&labels),
+            Some("fighter/l98cu")
+        );
+
+        let labels = HashLabels::from_string("fighter/l98cu\nfighter/pvdba");
+        assert_eq!(
+            hash40("fighter/pvdba").label(&labels),
+            Some("fighter/l98cu")
+        );
+    }
+
+    #[test]
+    fn write_sorted() {
+        let labels = HashLabels::from_string("b\na\r\nc\na\n");
+
+        let mut text = Vec::new();
+        labels.write_to_writer(&mut text).unwrap();
+        assert_eq!(text, b"a\nb\nc\n");
+    }
+
     #[test]
     fn from_string_line_feed() {
         let text = "a\nbc\ndef\n";
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io::{Read, Seek, Write};
+
+use binrw::{binrw, BinReaderExt, BinResult, BinWriterExt};
+
+use super::HashLabels;
+use crate::Hash40;
+
+/// The version of the binary label format written by [`HashLabels::write_binary`]
+pub const BINARY_LABELS_VERSION: u32 = 1;
+
+/// Magic at the start of a binary label file, used to tell it apart from a text label file
+pub(crate) const BINARY_LABELS_MAGIC: &[u8; 4] = b"HLBL";
+
+/// Size of the magic, version and counts before the table of entries
+const HEADER_SIZE: u64 = 0x10;
+
+/// Size of a [`BinaryLabelEntry`]
+const ENTRY_SIZE: u64 = 0x10;
+
+/// A label file which can be loaded without hashing every label. Made up of a table of hashes
+/// sorted in ascending order, each pointing to its label within a pool of UTF-8 strings.
+#[binrw]
+#[brw(little, magic = b"HLBL")]
+struct BinaryLabels {
+    #[br(assert(
+        version == BINARY_LABELS_VERSION,
+        "unsupported binary label version {}",
+        version
+    ))]
+    version: u32,
+
+    #[br(temp)]
+    #[bw(calc = entries.len() as u32)]
+    entry_count: u32,
+
+    #[br(temp)]
+    #[bw(calc = string_pool.len() as u32)]
+    string_pool_size: u32,
+
+    #[br(count = entry_count)]
+    entries: Vec<BinaryLabelEntry>,
+
+    #[br(count = string_pool_size)]
+    string_pool: Vec<u8>,
+}
+
+#[binrw]
+struct BinaryLabelEntry {
+    hash: Hash40,
+    /// Offset of the label within the string pool
+    offset: u32,
+    len: u32,
+}
+
+impl HashLabels {
+    /// Read labels in the binary format written by [`HashLabels::write_binary`]. The table must
+    /// be sorted by hash, so every hash is known to be unique and the labels are allocated at
+    /// their final size up front.
+    ///
+    /// This isn't zero-copy, as [`HashLabels`] owns its labels: each label is copied out of the
+    /// string pool once. Loading is still much faster than the text format, as nothing is hashed.
+    pub fn from_binary<R: Read + Seek>(reader: &mut R) -> BinResult<Self> {
+        let start = reader.stream_position()?;
+        let binary: BinaryLabels = reader.read_le()?;
+        let entries_pos = start + HEADER_SIZE;
+        let string_pool_pos = entries_pos + binary.entries.len() as u64 * ENTRY_SIZE;
+
+        let string_pool =
+            std::str::from_utf8(&binary.string_pool).map_err(|err| binrw::Error::Custom {
+                pos: string_pool_pos + err.valid_up_to() as u64,
+                err: Box::new(err),
+            })?;
+
+        let mut labels = HashMap::with_capacity(binary.entries.len());
+        let mut last_hash = None;
+        for (i, entry) in binary.entries.iter().enumerate() {
+            let pos = entries_pos + i as u64 * ENTRY_SIZE;
+            if last_hash.is_some_and(|last_hash| entry.hash <= last_hash) {
+                return Err(binrw::Error::AssertFail {
+                    pos,
+                    message: format!("{:?} isn't sorted after the previous hash", entry.hash),
+                });
+            }
+            last_hash = Some(entry.hash);
+
+            let start = entry.offset as usize;
+            let label = start
+                .checked_add(entry.len as usize)
+                .and_then(|end| string_pool.get(start..end))
+                .ok_or_else(|| binrw::Error::AssertFail {
+                    pos,
+                    message: format!("label of {:?} is outside of the string pool", entry.hash),
+                })?;
+
+            labels.insert(entry.hash, label.to_owned());
+        }
+
+        Ok(HashLabels { labels })
+    }
+
+    /// Write the labels in a binary format, which can be loaded faster than the text format as
+    /// labels don't need to be hashed. Read using [`HashLabels::from_binary`], or
+    /// [`HashLabels::from_file`] which detects the format.
+    pub fn write_binary<W: Write + Seek>(&self, writer: &mut W) -> BinResult<()> {
+        let mut labels = self.labels.iter().collect::<Vec<_>>();
+        labels.sort_unstable_by_key(|(hash, _)| **hash);
+
+        let mut string_pool = Vec::new();
+        let entries = labels
+            .into_iter()
+            .map(|(hash, label)| {
+                let entry = BinaryLabelEntry {
+                    hash: *hash,
+                    offset: string_pool.len() as u32,
+                    len: label.len() as u32,
+                };
+                string_pool.extend_from_slice(label.as_bytes());
+
+                entry
+            })
+            .collect();
+
+        writer.write_le(&BinaryLabels {
+            version: BINARY_LABELS_VERSION,
+            entries,
+            string_pool,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash40;
+    use binrw::io::Cursor;
+
+    #[test]
+    fn binary_round_trip() {
+        let labels = HashLabels::from_string("fighter\nfighter/mario\nstream:/sound/bgm");
+
+        let mut binary = Cursor::new(Vec::new());
+        labels.write_binary(&mut binary).unwrap();
+        assert_eq!(&binary.get_ref()[..4], BINARY_LABELS_MAGIC);
+
+        binary.set_position(0);
+        let read = HashLabels::from_binary(&mut binary).unwrap();
+        assert_eq!(read.labels, labels.labels);
+        assert_eq!(hash40("fighter/mario").label(&read), Some("fighter/mario"));
+    }
+
+    #[test]
+    fn binary_invalid() {
+        let labels = HashLabels::from_string("fighter");
+        let mut binary = Cursor::new(Vec::new());
+        labels.write_binary(&mut binary).unwrap();
+
+        // Unsupported version
+        let mut data = binary.get_ref().clone();
+        data[4] = 2;
+        assert!(HashLabels::from_binary(&mut Cursor::new(data)).is_err());
+
+        // Label outside of the string pool, reported at its entry
+        let mut data = binary.get_ref().clone();
+        data[0x18] = 0xFF;
+        assert!(matches!(
+            HashLabels::from_binary(&mut Cursor::new(data)),
+            Err(binrw::Error::AssertFail { pos: 0x10, .. })
+        ));
+    }
+
+    #[test]
+    fn binary_unsorted() {
+        let labels = HashLabels::from_string("fighter\nfighter/mario");
+        let mut binary = Cursor::new(Vec::new());
+        labels.write_binary(&mut binary).unwrap();
+
+        // Swap the hashes of both entries
+        let mut data = binary.into_inner();
+        let (first, second) = data[0x10..0x30].split_at_mut(0x10);
+        first[..8].swap_with_slice(&mut second[..8]);
+
+        assert!(matches!(
+            HashLabels::from_binary(&mut Cursor::new(data)),
+            Err(binrw::Error::AssertFail { pos: 0x20, .. })
+        ));
+    }
+}
@@ -0,0 +1,282 @@
+use std::collections::HashSet;
+
+use super::HashLabels;
+use crate::{hash40, ArcLookup, Hash40, HashToIndex, Region};
+
+/// Options for [`HashLabels::discover_from_arc`]
+#[derive(Default)]
+pub struct DiscoverOptions<'a> {
+    /// Also scan stream files (such as .nus3audio), which make up most of the size of the arc
+    pub include_streams: bool,
+
+    /// Only scan non-stream files with one of these extensions (such as `"prc"`), or every file
+    /// if empty
+    pub extensions: &'a [&'a str],
+}
+
+/// The hashes which can be labeled, along with which lengths they have so most candidates can be
+/// skipped without hashing them
+struct KnownHashes {
+    hashes: HashSet<Hash40>,
+    lengths: [bool; 0x100],
+    /// The length of the longest hash, at most 255 as that is the longest a [`Hash40`] can store
+    max_len: usize,
+}
+
+impl KnownHashes {
+    fn new<A: ArcLookup + ?Sized>(arc: &A) -> Self {
+        let file_paths = arc.get_file_paths().iter().flat_map(|path| {
+            [path.path, path.parent, path.file_name, path.ext].map(|hash| hash.hash40())
+        });
+        let dirs = arc
+            .get_dir_hash_to_info_index()
+            .iter()
+            .map(HashToIndex::hash40);
+        let streams = arc
+            .get_stream_hash_to_entries()
+            .iter()
+            .map(HashToIndex::hash40);
+
+        Self::from_hashes(file_paths.chain(dirs).chain(streams).collect())
+    }
+
+    fn from_hashes(hashes: HashSet<Hash40>) -> Self {
+        let mut lengths = [false; 0x100];
+        for hash in &hashes {
+            lengths[hash.len() as usize] = true;
+        }
+        let max_len = lengths.iter().rposition(|known| *known).unwrap_or(0);
+
+        Self {
+            hashes,
+            lengths,
+            max_len,
+        }
+    }
+
+    fn contains(&self, candidate: &str) -> bool {
+        candidate.len() < self.lengths.len()
+            && self.lengths[candidate.len()]
+            && self.hashes.contains(&hash40(candidate))
+    }
+}
+
+/// Whether a byte can be part of a path, including the `:` of `stream:` and `prebuilt:`
+fn is_path_byte(byte: u8) -> bool {
+    matches!(byte, b'a'..=b'z' | b'0'..=b'9' | b'_' | b'-' | b'+' | b'.' | b'/' | b':')
+}
+
+fn is_word_byte(byte: u8) -> bool {
+    byte.is_ascii_lowercase() || byte.is_ascii_digit()
+}
+
+/// Find every path-like string within `data` whose hash (or the hash of one of its parent
+/// directories) is known, adding it to `found`
+fn scan<'a>(data: &'a [u8], known: &KnownHashes, found: &mut HashSet<&'a str>) {
+    for run in data.split(|byte| !is_path_byte(*byte)) {
+        // Only made up of ASCII, so this can't fail
+        let run = std::str::from_utf8(run).unwrap();
+
+        // Paths may be preceded by other characters, so try starting at each word
+        let starts = (0..run.len()).filter(|&start| {
+            start == 0
+                || !is_word_byte(run.as_bytes()[start - 1]) && is_word_byte(run.as_bytes()[start])
+        });
+
+        for start in starts {
+            let candidate = &run[start..];
+
+            // Parents longer than the longest known hash can't match, so only that much of the
+            // run needs searching. Otherwise a long run would take quadratic time.
+            let searched = &candidate[..candidate.len().min(known.max_len + 1)];
+            let parents = searched
+                .match_indices('/')
+                .map(|(end, _)| &candidate[..end]);
+
+            for candidate in std::iter::once(candidate).chain(parents) {
+                if known.contains(candidate) {
+                    found.insert(candidate);
+                }
+            }
+        }
+    }
+}
+
+impl HashLabels {
+    /// Discover labels by scanning the contents of the files within an arc for path-like strings.
+    /// Only strings which hash to a path, directory, file name or extension in the arc are kept.
+    ///
+    /// Returns the labels which weren't already known, in sorted order. Files which fail to be
+    /// read are skipped.
+    pub fn discover_from_arc<A: ArcLookup + ?Sized>(
+        &mut self,
+        arc: &A,
+        region: Region,
+        options: DiscoverOptions<'_>,
+    ) -> Vec<String> {
+        let known = KnownHashes::new(arc);
+        let extensions: HashSet<Hash40> =
+            options.extensions.iter().map(|ext| hash40(ext)).collect();
+
+        // Files sharing data only need to be scanned once
+        let mut scanned = HashSet::new();
+        let files = arc
+            .get_file_paths()
+            .iter()
+            .filter(|path| extensions.is_empty() || extensions.contains(&path.ext.hash40()))
+            .map(|path| path.path.hash40())
+            .filter(|hash| {
+                arc.get_file_offset_from_hash(*hash, region)
+                    .is_ok_and(|offset| scanned.insert(offset))
+            })
+            .collect::<Vec<_>>();
+
+        let streams = if options.include_streams {
+            arc.get_stream_hash_to_entries()
+                .iter()
+                .map(HashToIndex::hash40)
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let mut new_labels = HashSet::new();
+        for hash in files {
+            if let Ok(data) = arc.get_nonstream_file_contents(hash, region) {
+                self.add_discovered(&data, &known, &mut new_labels);
+            }
+        }
+        for hash in streams {
+            if let Ok(data) = arc.get_stream_file_contents(hash, region) {
+                self.add_discovered(&data, &known, &mut new_labels);
+            }
+        }
+
+        let mut new_labels = new_labels.into_iter().collect::<Vec<_>>();
+        new_labels.sort_unstable();
+
+        new_labels
+    }
+
+    fn add_discovered(
+        &mut self,
+        data: &[u8],
+        known: &KnownHashes,
+        new_labels: &mut HashSet<String>,
+    ) {
+        let mut found = HashSet::new();
+        scan(data, known, &mut found);
+
+        for label in found {
+            if hash40(label).label(self).is_none() {
+                self.add_label(label);
+                new_labels.insert(label.to_owned());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arc_file::tests::test_arc;
+    use crate::{Compression, SharedFileMode};
+
+    #[test]
+    fn scan_embedded_paths() {
+        let mut arc = test_arc();
+        arc.replace_file(
+            "fighter/mario/model.numdlb",
+            Region::UsEnglish,
+            b"\x00\x05fighter/mario/alias.numdlb\x00\xFFmodel.numdlb;fighter/luigi/c00\x00",
+            Compression::Uncompressed,
+            SharedFileMode::Unshare,
+        )
+        .unwrap();
+
+        let mut labels = HashLabels::new();
+        labels.add_label("fighter");
+
+        let found = labels.discover_from_arc(&arc, Region::UsEnglish, Default::default());
+        assert_eq!(
+            found,
+            [
+                "alias.numdlb",
+                "fighter/mario",
+                "fighter/mario/alias.numdlb",
+                "model.numdlb",
+                "numdlb"
+            ]
+        );
+        assert_eq!(
+            hash40("fighter/mario/alias.numdlb").label(&labels),
+            Some("fighter/mario/alias.numdlb")
+        );
+        assert!(hash40("fighter/luigi/c00").label(&labels).is_none());
+
+        // Nothing new is found the second time
+        let found = labels.discover_from_arc(&arc, Region::UsEnglish, Default::default());
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn scan_long_run() {
+        let known = KnownHashes::from_hashes(
+            ["fighter/mario", "fighter/mario/alias.numdlb"]
+                .iter()
+                .map(|path| hash40(path))
+                .collect(),
+        );
+
+        // A run of hundreds of kilobytes, which previously took quadratic time to scan
+        let mut data = b"a/".repeat(100_000);
+        data.extend_from_slice(b";fighter/mario/alias.numdlb/");
+        data.extend_from_slice(&b"b/".repeat(100_000));
+
+        let mut found = HashSet::new();
+        scan(&data, &known, &mut found);
+
+        let mut found: Vec<_> = found.into_iter().collect();
+        found.sort_unstable();
+        assert_eq!(found, ["fighter/mario", "fighter/mario/alias.numdlb"]);
+    }
+
+    #[test]
+    fn scan_filtered_by_extension() {
+        let mut arc = test_arc();
+        arc.replace_file(
+            "fighter/mario/model.numdlb",
+            Region::UsEnglish,
+            b"\x00fighter/mario/alias.numdlb\x00",
+            Compression::Uncompressed,
+            SharedFileMode::Unshare,
+        )
+        .unwrap();
+
+        // The path is only embedded in a numdlb, so filtering to prc files skips it
+        let mut labels = HashLabels::new();
+        let options = DiscoverOptions {
+            extensions: &["prc"],
+            ..Default::default()
+        };
+        assert!(labels
+            .discover_from_arc(&arc, Region::UsEnglish, options)
+            .is_empty());
+        assert!(hash40("fighter/mario/alias.numdlb")
+            .label(&labels)
+            .is_none());
+
+        let options = DiscoverOptions {
+            extensions: &["prc", "numdlb"],
+            ..Default::default()
+        };
+        assert!(labels
+            .discover_from_arc(&arc, Region::UsEnglish, options)
+            .contains(&"fighter/mario/alias.numdlb".to_owned()));
+
+        let mut labels = HashLabels::new();
+        assert!(labels
+            .discover_from_arc(&arc, Region::UsEnglish, Default::default())
+            .contains(&"fighter/mario/alias.numdlb".to_owned()));
+    }
+}
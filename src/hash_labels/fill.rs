@@ -0,0 +1,181 @@
+use super::HashLabels;
+use crate::{hash40, ArcFile, FileSystem, Hash40, SearchFileSystem, SearchListEntry};
+
+/// The hashes of a path and its components, which each path or directory in the tables stores
+#[derive(Debug, Clone, Copy)]
+struct PathParts {
+    path: Hash40,
+    /// An empty (zero) hash for top level paths
+    parent: Hash40,
+    name: Hash40,
+    /// Only stored for files
+    ext: Option<Hash40>,
+}
+
+impl From<&SearchListEntry> for PathParts {
+    fn from(entry: &SearchListEntry) -> Self {
+        Self {
+            path: entry.path.hash40(),
+            parent: entry.parent.hash40(),
+            name: entry.file_name.hash40(),
+            ext: Some(entry.ext.hash40()),
+        }
+    }
+}
+
+fn file_system_parts(fs: &FileSystem) -> impl Iterator<Item = PathParts> + '_ {
+    let files = fs.file_paths.iter().map(|path| PathParts {
+        path: path.path.hash40(),
+        parent: path.parent.hash40(),
+        name: path.file_name.hash40(),
+        ext: Some(path.ext.hash40()),
+    });
+
+    let dirs = fs.dir_infos.iter().map(|dir| PathParts {
+        path: dir.path.hash40(),
+        parent: dir.parent,
+        name: dir.name,
+        ext: None,
+    });
+
+    files.chain(dirs)
+}
+
+fn search_file_system_parts(search: &SearchFileSystem) -> impl Iterator<Item = PathParts> + '_ {
+    let folders = search
+        .folders
+        .iter()
+        .map(|folder| PathParts::from(&folder.0));
+    let paths = search.paths.iter().map(|path| PathParts::from(&path.0));
+
+    folders.chain(paths)
+}
+
+impl HashLabels {
+    /// Derive missing labels from the components stored for each path and directory of a
+    /// [`FileSystem`]. A path is labeled by joining its labeled parent and name, and a parent,
+    /// name and extension are labeled by splitting a labeled path. Only labels matching the stored
+    /// hashes are added.
+    ///
+    /// This is repeated until no more labels can be derived, returning how many were added.
+    pub fn fill_from_filesystem(&mut self, fs: &FileSystem) -> usize {
+        let parts = file_system_parts(fs).collect::<Vec<_>>();
+
+        self.fill(&parts)
+    }
+
+    /// The same as [`HashLabels::fill_from_filesystem`], also using the paths and folders of the
+    /// arc's [`SearchFileSystem`]
+    pub fn fill_from_arc(&mut self, arc: &ArcFile) -> usize {
+        let parts = file_system_parts(&arc.file_system)
+            .chain(search_file_system_parts(&arc.search_file_system))
+            .collect::<Vec<_>>();
+
+        self.fill(&parts)
+    }
+
+    fn fill(&mut self, parts: &[PathParts]) -> usize {
+        let mut added = 0;
+
+        loop {
+            let added_in_pass = parts
+                .iter()
+                .map(|parts| self.fill_parts(parts))
+                .sum::<usize>();
+            if added_in_pass == 0 {
+                break added;
+            }
+
+            added += added_in_pass;
+        }
+    }
+
+    /// Derive any missing labels of a single path, returning how many were added
+    fn fill_parts(&mut self, parts: &PathParts) -> usize {
+        let mut added = 0;
+
+        if let Some(path) = parts.path.label(self).map(str::to_owned) {
+            let (parent, name) = match path.rsplit_once('/') {
+                Some((parent, name)) => (parent, name),
+                None => ("", &path[..]),
+            };
+
+            added += self.add_derived(parts.parent, parent) as usize;
+            added += self.add_derived(parts.name, name) as usize;
+            if let (Some(ext), Some((_, ext_label))) = (parts.ext, name.rsplit_once('.')) {
+                added += self.add_derived(ext, ext_label) as usize;
+            }
+        } else {
+            let path = match (parts.parent.as_u64(), parts.name.label(self)) {
+                (0, Some(name)) => Some(name.to_owned()),
+                (_, Some(name)) => parts
+                    .parent
+                    .label(self)
+                    .map(|parent| format!("{}/{}", parent.trim_end_matches('/'), name)),
+                (_, None) => None,
+            };
+
+            if let Some(path) = path {
+                added += self.add_derived(parts.path, &path) as usize;
+            }
+        }
+
+        added
+    }
+
+    /// Add a derived label if it matches the expected hash and the hash isn't labeled yet
+    fn add_derived(&mut self, hash: Hash40, label: &str) -> bool {
+        let is_new = hash.as_u64() != 0 && !label.is_empty() && hash.label(self).is_none();
+
+        if is_new && hash40(label) == hash {
+            self.add_label(label);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arc_file::tests::test_arc;
+    use crate::filesystem::tests::test_file_system;
+
+    #[test]
+    fn fill_from_path() {
+        let mut labels = HashLabels::new();
+        labels.add_label("fighter/mario/model.numdlb");
+
+        // The file's path labels fighter/mario, whose DirInfo then labels fighter and mario
+        assert_eq!(labels.fill_from_filesystem(&test_file_system()), 5);
+        for label in [
+            "fighter",
+            "fighter/mario",
+            "mario",
+            "model.numdlb",
+            "numdlb",
+        ] {
+            assert_eq!(hash40(label).label(&labels), Some(label));
+        }
+        assert!(hash40("fighter/mario/alias.numdlb")
+            .label(&labels)
+            .is_none());
+    }
+
+    #[test]
+    fn fill_from_components() {
+        let mut labels = HashLabels::new();
+        labels.add_label("fighter/mario");
+        labels.add_label("alias.numdlb");
+
+        labels.fill_from_arc(&test_arc());
+        assert_eq!(
+            hash40("fighter/mario/alias.numdlb").label(&labels),
+            Some("fighter/mario/alias.numdlb")
+        );
+
+        // Nothing left to derive
+        assert_eq!(labels.fill_from_arc(&test_arc()), 0);
+    }
+}
@@ -3,7 +3,7 @@
 //! ```rust
 //! use smash_arc::{ArcFile, ArcLookup, FileNode, Hash40, Region};
 //!
-//! // Load the hashes needed to list directories (file format restriction)
+//! // Load the labels used to name files and directories
 //! Hash40::set_global_labels_file("hash_labels.txt").unwrap();
 //!
 //! // Parse the arc from a file
@@ -27,30 +27,56 @@
 //!
 //! ## Cargo Features
 //!
-//! * `network` (enabled by default) = Ability to parse the file over the network
+//! * `network` (enabled by default) = Ability to parse the file over the network, or serve one
+//!   to other machines using [`NetworkServer`]
+//! * `server` = Build the `arc-server` binary for serving a data.arc over the network
+//! * `cli` = Build the `smash-arc` binary for listing, extracting and inspecting files
 //! * `dir-listing` (enabled by default) = List directories
 //! * `global-hashes` (enabled by default) = Enable a global table for cracking hashes
 //! * `smash-runtime` = Enables features for running under the context of Smash Ultimate itself
 //! (enable Aarch64 crc32 hardware acceleration, enable parsing the Arc from the game's memory
 //! layout)
 //! * `search` = Enable functionality to fuzzy search [`ArcFile`]s
+//! * `mmap` = Enable opening [`ArcFile`]s using a memory map ([`ArcFile::open_mmap`])
+//! * `parallel` = Enable extracting directories using multiple threads
+//!   ([`ArcFile::extract_dir_parallel`])
+//! * `crack` = Enable cracking unknown hashes from templates and wordlists using multiple threads
+//!   ([`crack::Cracker`])
+//! * `async` = Enable reading [`ArcFile`]s from an async (tokio) reader using [`AsyncArcFile`]
 //!
 //! * ZSTD backends
 //!   * `libzstd` - Recommended for use on platforms it builds for
 //!   * `rust-zstd` - Increased portability (Recommended for use on switch)
 //!   * `nozstd` - Provide no zstd backend, panic on ZSTD decompression
 
+mod add;
+#[cfg(feature = "async")]
+mod async_arc;
+#[cfg(feature = "crack")]
+pub mod crack;
+#[cfg(feature = "dir-listing")]
+mod extract;
+mod file_reader;
 mod filesystem;
 mod hash40;
 mod hash_labels;
 mod lookups;
+#[cfg(feature = "mmap")]
+mod mmap;
+mod read_at;
 mod region;
+mod replace;
 mod table_indices;
 mod zstd_backend;
 
 #[cfg(feature = "ffi-bindings")]
 mod ffi_bindings;
 
+#[cfg(feature = "network")]
+mod network_reader;
+#[cfg(feature = "network")]
+mod network_server;
+
 #[cfg(feature = "search")]
 mod search;
 
@@ -62,10 +88,25 @@ mod loaded_arc;
 pub use loaded_arc::*;
 
 pub use arc_file::*;
+#[cfg(feature = "async")]
+pub use async_arc::AsyncArcFile;
+#[cfg(feature = "dir-listing")]
+pub use extract::{label_to_path, ExtractOptions, ExtractProgress};
+pub use file_reader::FileReader;
 pub use filesystem::*;
-pub use hash40::{hash40, Hash40};
-pub use hash_labels::{HashLabels, GLOBAL_LABELS};
-pub use lookups::{ArcLookup, LookupError, SearchLookup};
+pub use hash40::{hash40, Hash40, Hash40Builder, ParseHash40Error};
+#[cfg(feature = "global-hashes")]
+pub use hash_labels::GLOBAL_LABELS;
+pub use hash_labels::{DiscoverOptions, HashLabels, LabelCollision, BINARY_LABELS_VERSION};
+pub use lookups::{
+    ArcLookup, LookupError, SearchLookup, WalkdirDirectoryType, WalkdirEntry, WalkdirIter,
+};
+#[cfg(feature = "network")]
+pub use network_reader::NetworkReader;
+#[cfg(feature = "network")]
+pub use network_server::{NetworkServer, DEFAULT_PORT};
+pub use read_at::ReadAt;
+pub use replace::{Compression, SharedFileMode};
 pub use table_indices::*;
 
 #[cfg(feature = "serialize")]
@@ -1,11 +1,30 @@
-use std::{fs::File, io::BufReader};
+use std::io;
+use std::sync::OnceLock;
 
 use binrw::{BinReaderExt, BinResult};
 
 pub use crate::filesystem::*;
+use crate::read_at::{open_read_at, ReadAtFile};
 use crate::ArcFile;
 use crate::SeekRead;
 
+/// The path of the data.arc the game has loaded
+const DATA_ARC_PATH: &str = "rom:/data.arc";
+
+static DATA_ARC: OnceLock<ReadAtFile> = OnceLock::new();
+
+/// The data.arc the game has loaded, opened once and shared by every read from a [`LoadedArc`]
+/// rather than reopened for each read
+pub(crate) fn data_arc() -> io::Result<&'static ReadAtFile> {
+    if let Some(file) = DATA_ARC.get() {
+        return Ok(file);
+    }
+
+    let file = open_read_at(DATA_ARC_PATH)?;
+
+    Ok(DATA_ARC.get_or_init(|| file))
+}
+
 #[repr(C)]
 #[derive(Debug)]
 pub struct LoadedArc {
@@ -52,7 +71,7 @@ pub struct LoadedArc {
 
 impl LoadedArc {
     pub fn open() -> BinResult<ArcFile> {
-        Self::from_reader(BufReader::new(File::open("rom:/data.arc")?))
+        ArcFile::open(DATA_ARC_PATH)
     }
 
     pub fn from_reader<R: SeekRead + Send + 'static>(mut reader: R) -> BinResult<ArcFile> {
@@ -1,11 +1,12 @@
 use crate::*;
-use std::io::{self, Read, Seek, SeekFrom};
 use std::ops::Range;
+use std::sync::Mutex;
 
 use region::Region;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum LookupError {
     #[error("failed to read the file")]
     FileRead(#[from] std::io::Error),
@@ -18,11 +19,20 @@ pub enum LookupError {
 
     #[error("the requested resource for the given regional index could not be found")]
     InvalidRegion,
+
+    #[error("a resource already exists at the given path")]
+    AlreadyExists,
+
+    #[error("the given path has no parent directory")]
+    InvalidPath,
 }
 
 mod arc_file;
 #[cfg(feature = "smash-runtime")]
 mod loaded_arc;
+mod walkdir;
+
+pub use walkdir::{WalkdirDirectoryType, WalkdirEntry, WalkdirIter};
 
 /// The trait that allows different implementations of the arc to share the same code for making
 /// lookups into the filesystem use the same logic.
@@ -41,6 +51,10 @@ pub trait ArcLookup {
     fn get_file_datas(&self) -> &[FileData];
     fn get_folder_offsets(&self) -> &[DirectoryOffset];
 
+    /// The child directories of every [`DirInfo`], used by [`ArcLookup::walk_dir`] to walk
+    /// subdirectories
+    fn get_folder_child_hashes(&self) -> &[HashToIndex];
+
     fn get_stream_entries(&self) -> &[StreamEntry];
     fn get_stream_file_indices(&self) -> &[u32];
     fn get_stream_datas(&self) -> &[StreamData];
@@ -48,6 +62,13 @@ pub trait ArcLookup {
     fn get_stream_hash_to_entries(&self) -> &[HashToIndex];
 
     fn get_file_reader<'a>(&'a self) -> Box<dyn SeekRead + 'a>;
+
+    /// A reader over the arc for positional reads, used by [`ArcLookup::read_file_data`]. By
+    /// default this locks a reader from [`ArcLookup::get_file_reader`] for each read, implement
+    /// this to allow reading without seeking or locking.
+    fn get_file_reader_at<'a>(&'a self) -> Box<dyn ReadAt + 'a> {
+        Box::new(Mutex::new(self.get_file_reader()))
+    }
     fn get_file_section_offset(&self) -> u64;
     fn get_stream_section_offset(&self) -> u64;
     fn get_shared_section_offset(&self) -> u64;
@@ -73,6 +94,41 @@ pub trait ArcLookup {
             })
     }
 
+    /// Open a file (or stream file) for reading without reading all of its contents into memory
+    /// first. Compressed files are decompressed incrementally as the [`FileReader`] is read.
+    fn open_file<Hash: Into<Hash40>>(
+        &self,
+        hash: Hash,
+        region: Region,
+    ) -> Result<FileReader<'_>, LookupError> {
+        fn inner<Arc: ArcLookup + ?Sized>(
+            arc: &Arc,
+            hash: Hash40,
+            region: Region,
+        ) -> Result<FileReader<'_>, LookupError> {
+            match arc.get_file_info_from_hash(hash) {
+                Ok(file_info) => {
+                    let folder_offset = arc.get_folder_offset(file_info, region);
+                    let file_data = arc.get_file_data(file_info, region);
+
+                    arc.open_file_data(file_data, folder_offset)
+                }
+                Err(LookupError::Missing) => {
+                    let stream_data = arc.get_stream_data(hash, region)?;
+
+                    Ok(FileReader::raw(
+                        arc.get_file_reader_at(),
+                        stream_data.offset,
+                        stream_data.size,
+                    ))
+                }
+                Err(err) => Err(err),
+            }
+        }
+
+        inner(self, hash.into(), region)
+    }
+
     fn get_dir_info_from_hash<Hash: Into<Hash40>>(
         &self,
         hash: Hash,
@@ -94,6 +150,15 @@ pub trait ArcLookup {
         inner(self, hash.into())
     }
 
+    /// Recursively walk every file within a directory, following redirections. See
+    /// [`WalkdirIter`].
+    fn walk_dir<Hash: Into<Hash40>>(
+        &self,
+        hash: Hash,
+    ) -> Result<WalkdirIter<'_, Self>, LookupError> {
+        Ok(WalkdirIter::new(self, self.get_dir_info_from_hash(hash)?))
+    }
+
     fn get_dir_info_from_hash_mut<Hash: Into<Hash40>>(
         &mut self,
         hash: Hash,
@@ -185,22 +250,11 @@ pub trait ArcLookup {
     }
 
     fn read_stream_file_data(&self, file_data: &StreamData) -> Result<Vec<u8>, LookupError> {
-        let offset = file_data.offset;
-
-        let mut reader = self.get_file_reader();
-        reader.seek(SeekFrom::Start(offset))?;
+        let mut data = vec![0; file_data.size as usize];
+        self.get_file_reader_at()
+            .read_exact_at(&mut data, file_data.offset)?;
 
-        let mut data = Vec::with_capacity(file_data.size as usize);
-        let mut reader = Read::take(&mut reader, file_data.size as u64);
-
-        if reader.read_to_end(&mut data)? as u64 == file_data.size {
-            Ok(data)
-        } else {
-            Err(LookupError::FileRead(io::Error::new(
-                io::ErrorKind::UnexpectedEof,
-                "Failed to read data",
-            )))
-        }
+        Ok(data)
     }
 
     fn get_shared_files(&self, hash: Hash40, region: Region) -> Result<Vec<Hash40>, LookupError> {
@@ -377,20 +431,45 @@ pub trait ArcLookup {
             return Err(LookupError::UnsupportedCompression);
         }
 
-        let mut data = Vec::with_capacity(file_data.decomp_size as usize);
-
-        let mut reader = self.get_file_reader();
-        //let mut reader = self.reader.lock().unwrap();
-        reader.seek(SeekFrom::Start(offset))?;
-        let mut reader = Read::take(&mut reader, file_data.comp_size as u64);
+        let mut raw = vec![0; file_data.comp_size as usize];
+        self.get_file_reader_at().read_exact_at(&mut raw, offset)?;
 
         if file_data.flags.compressed() {
-            crate::zstd_backend::copy_decode(reader, &mut data)?;
+            let mut data = Vec::with_capacity(file_data.decomp_size as usize);
+            crate::zstd_backend::copy_decode(&raw[..], &mut data)?;
+
+            Ok(data)
         } else {
-            io::copy(&mut reader, &mut data)?;
+            Ok(raw)
         }
+    }
 
-        Ok(data)
+    fn open_file_data(
+        &self,
+        file_data: &FileData,
+        folder_offset: u64,
+    ) -> Result<FileReader<'_>, LookupError> {
+        let offset = folder_offset
+            + self.get_file_section_offset()
+            + ((file_data.offset_in_folder as u64) << 2);
+
+        if file_data.flags.compressed() && !file_data.flags.use_zstd() {
+            return Err(LookupError::UnsupportedCompression);
+        }
+
+        let reader = self.get_file_reader_at();
+        let comp_size = file_data.comp_size as u64;
+
+        if file_data.flags.compressed() {
+            Ok(FileReader::compressed(
+                reader,
+                offset,
+                comp_size,
+                file_data.decomp_size as u64,
+            )?)
+        } else {
+            Ok(FileReader::raw(reader, offset, comp_size))
+        }
     }
 
     fn get_file_offset_from_hash(&self, hash: Hash40, region: Region) -> Result<u64, LookupError> {
@@ -845,123 +924,3 @@ mod tests {
         dir_info_print_filepaths(&arc, &dir_info, &labels);
     }
 }
-
-// #[derive(Copy, Clone)]
-// enum WalkdirDirectoryType<'a> {
-//     FileGroup(&'a DirectoryOffset),
-//     Directory(&'a DirInfo)
-// }
-
-// pub struct WalkdirIter<'a> {
-//     arc: &'a LoadedArc,
-//     current: WalkdirDirectoryType<'a>,
-//     index: usize,
-//     child_index: usize,
-//     redirect: Option<WalkdirIter<'a, T>>,
-//     child: Option<WalkdirIter<'a, T>>
-// }
-
-// impl<'a> WalkdirIter<'a> {
-//     fn get_file_count(&self) -> usize {
-//         match self.current {
-//             WalkdirDirectoryType::Directory(directory) => directory.file_count as usize,
-//             WalkdirDirectoryType::FileGroup(group) => group.file_count as usize
-//         }
-//     }
-
-//     fn setup_redirect(&mut self) {
-//         if self.redirect.is_none() {
-//             if let WalkdirDirectoryType::Directory(directory) = self.current {
-//                 if directory.flags.redirected() {
-//                     let file_group = &arc.get_folder_offsets()[directory.path.index() as usize];
-//                     if file_group.directory_index == 0xFF_FFFF { return; }
-//                     if directory.flags.is_symlink() {
-//                         self.redirect = Self {
-//                             arc: self.arc,
-//                             current: WalkdirDirectoryType::Directory(&arc.get_dir_infos()[file_group.directory_index as usize]),
-//                             index: 0,
-//                             child_index: 0,
-//                             redirect: None,
-//                             child: None
-//                         };
-//                     } else {
-//                         self.redirect = Self {
-//                             arc: self.arc,
-//                             current: WalkdirDirectoryType::FileGroup(&arc.get_folder_offsets()[file_group.directory_index as usize]),
-//                             index: 0,
-//                             child_index: 0,
-//                             redirect: None,
-//                             child: None
-//                         };
-//                     }
-//                 }
-//             }
-//         }
-//     }
-
-//     fn setup_children(&mut self) {
-//         if let WalkdirDirectoryType::Directory(directory) = self.current {
-//             if self.child_index < directory.child_dir_count {
-
-//             }
-//         }
-//     }
-
-//     pub fn new(arc: &'a LoadedArc, index: u32) -> Self {
-//         Self {
-//             arc,
-//             current: WalkdirDirectoryType::Directory(&arc.get_dir_infos()[index as usize]),
-//             index: 0,
-//             child_index: 0,
-//             redirect: None,
-//             child: None
-//         }
-//     }
-
-//     pub fn new_hash(arc: &'a LoadedArc, hash: Hash40) -> Self {
-//         Self {
-//             arc,
-//             current: WalkdirDirectoryType::Directory(arc.get_dir_info_from_hash(hash).unwrap()),
-//             index: 0,
-//             child_index: 0,
-//             redirect: None,
-//             child: None
-//         }
-//     }
-// }
-
-// impl<'a> Iterator for WalkdirIter<'a> {
-//     type Output = WalkdirEntry<'a>;
-
-//     fn next(&mut self) -> Option<Self::Output> {
-//         if self.index < self.get_file_count() {
-//             self.index += 1;
-//             Some(WalkdirEntry {
-//                 arc: self.arc,
-//                 parent: self.current,
-//                 index: index - 1
-//             })
-//         } else {
-
-//         }
-//     }
-// }
-
-// pub struct WalkdirEntry<'a> {
-//     arc: &'a T,
-//     parent: WalkdirDirectoryType<'a>,
-//     index: usize
-// }
-
-// impl<'a> WalkdirEntry<'a> {
-//     pub fn parent(&self) -> WalkdirDirectoryType<'a> {
-//         self.parent
-//     }
-
-//     pub fn get(&self) -> &'a FileInfo {
-//         let range = match self.parent {
-//             WalkdirDirectoryType::FileGroup(group) => group.range(),
-//             WalkdirDirectoryType::Directory(directory) => directory.file_info_range()
-//         };
-//     }
-// }
@@ -62,6 +62,10 @@ impl ArcLookup for ArcFile {
         &mut self.file_system.folder_offsets
     }
 
+    fn get_folder_child_hashes(&self) -> &[HashToIndex] {
+        &self.file_system.folder_child_hashes
+    }
+
     fn get_stream_entries(&self) -> &[StreamEntry] {
         &self.file_system.stream_entries
     }
@@ -95,7 +99,67 @@ impl ArcLookup for ArcFile {
     }
 
     fn get_file_reader<'a>(&'a self) -> Box<dyn SeekRead + 'a> {
-        Box::new(MutexReader(self.reader.lock().unwrap()))
+        #[cfg(feature = "mmap")]
+        if let Some(mmap) = &self.mmap {
+            return self.patch_reader(io::Cursor::new(&mmap[..]));
+        }
+
+        self.patch_reader(MutexReader(self.reader.lock().unwrap()))
+    }
+
+    fn get_file_reader_at<'a>(&'a self) -> Box<dyn ReadAt + 'a> {
+        match &self.reader_at {
+            Some(reader) => self.patch_read_at(&**reader),
+            None => self.patch_read_at(Mutex::new(MutexReader(self.reader.lock().unwrap()))),
+        }
+    }
+}
+
+/// Where the data of a file is stored in the arc
+#[cfg(any(feature = "mmap", feature = "parallel"))]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DataLocation {
+    pub(crate) offset: u64,
+    pub(crate) size: u64,
+    pub(crate) compressed: bool,
+}
+
+#[cfg(any(feature = "mmap", feature = "parallel"))]
+impl ArcFile {
+    /// Find where the data of a file (or stream file) for the given region is stored
+    pub(crate) fn data_location(
+        &self,
+        hash: Hash40,
+        region: Region,
+    ) -> Result<DataLocation, LookupError> {
+        match self.get_file_info_from_hash(hash) {
+            Ok(file_info) => {
+                let folder_offset = self.get_folder_offset(file_info, region);
+                let file_data = self.get_file_data(file_info, region);
+
+                if file_data.flags.compressed() && !file_data.flags.use_zstd() {
+                    return Err(LookupError::UnsupportedCompression);
+                }
+
+                Ok(DataLocation {
+                    offset: folder_offset
+                        + self.file_section_offset
+                        + ((file_data.offset_in_folder as u64) << 2),
+                    size: file_data.comp_size as u64,
+                    compressed: file_data.flags.compressed(),
+                })
+            }
+            Err(LookupError::Missing) => {
+                let stream_data = self.get_stream_data(hash, region)?;
+
+                Ok(DataLocation {
+                    offset: stream_data.offset,
+                    size: stream_data.size,
+                    compressed: false,
+                })
+            }
+            Err(err) => Err(err),
+        }
     }
 }
 
@@ -121,7 +185,7 @@ impl SearchLookup for ArcFile {
     }
 }
 
-use std::sync::MutexGuard;
+use std::sync::{Mutex, MutexGuard};
 
 // Wrapper type for implementing Read + Seek for MutexGuard
 #[repr(transparent)]
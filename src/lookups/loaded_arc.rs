@@ -1,9 +1,10 @@
-use std::{fs::File, io::BufReader, slice};
+use std::{io::BufReader, slice};
 
 use crate::filesystem::*;
-use crate::loaded_arc::{LoadedArc, LoadedSearchSection};
-use crate::SeekRead;
+use crate::loaded_arc::{data_arc, LoadedArc, LoadedSearchSection};
+use crate::read_at::ReadAtCursor;
 use crate::{ArcLookup, SearchLookup};
+use crate::{ReadAt, SeekRead};
 
 impl ArcLookup for LoadedArc {
     fn get_file_info_buckets(&self) -> &[FileInfoBucket] {
@@ -117,6 +118,14 @@ impl ArcLookup for LoadedArc {
         }
     }
 
+    fn get_folder_child_hashes(&self) -> &[HashToIndex] {
+        unsafe {
+            let fs = *self.fs_header;
+            let table_size = fs.hash_folder_count;
+            slice::from_raw_parts(self.folder_child_hashes, table_size as _)
+        }
+    }
+
     fn get_folder_offsets_mut(&mut self) -> &mut [DirectoryOffset] {
         unsafe {
             let fs = *self.fs_header;
@@ -178,7 +187,13 @@ impl ArcLookup for LoadedArc {
     }
 
     fn get_file_reader<'a>(&'a self) -> Box<dyn SeekRead + 'a> {
-        Box::new(BufReader::new(File::open("rom:/data.arc").unwrap()))
+        Box::new(BufReader::new(ReadAtCursor::new(
+            data_arc().expect("failed to open rom:/data.arc"),
+        )))
+    }
+
+    fn get_file_reader_at<'a>(&'a self) -> Box<dyn ReadAt + 'a> {
+        Box::new(data_arc().expect("failed to open rom:/data.arc"))
     }
 }
 
@@ -0,0 +1,276 @@
+use std::slice;
+
+use crate::{ArcLookup, DirInfo, DirectoryOffset, FileInfo};
+
+/// Where the files of a directory being walked by a [`WalkdirIter`] are listed
+#[derive(Debug, Clone, Copy)]
+pub enum WalkdirDirectoryType<'a> {
+    /// A directory listing its own files, or the directory a symlinked directory points to
+    Directory(&'a DirInfo),
+    /// The group of files a directory shares with other directories
+    FileGroup(&'a DirectoryOffset),
+}
+
+/// A file found by a [`WalkdirIter`]
+#[derive(Debug, Clone, Copy)]
+pub struct WalkdirEntry<'a> {
+    /// The file itself
+    pub file_info: &'a FileInfo,
+    /// The directory being walked which contains the file
+    pub directory: &'a DirInfo,
+    /// If `directory` is redirected and the file came from its redirection (see
+    /// [`ArcLookup::get_directory_dependency`]), where the file is actually listed
+    pub redirect: Option<WalkdirDirectoryType<'a>>,
+}
+
+/// An iterator which recursively walks every file within a [`DirInfo`], created using
+/// [`ArcLookup::walk_dir`]. Does not depend on labels.
+///
+/// Each directory yields its own files, followed by the files of the directory or file group it
+/// is redirected to (if any), followed by the contents of each of its child directories.
+pub struct WalkdirIter<'a, Arc: ArcLookup + ?Sized> {
+    arc: &'a Arc,
+    /// Directories which have yet to be walked, in reverse order
+    dirs: Vec<&'a DirInfo>,
+    /// The directory currently being walked
+    current: Option<&'a DirInfo>,
+    /// Files of the current directory and its redirection which have yet to be yielded
+    files: Vec<(slice::Iter<'a, FileInfo>, Option<WalkdirDirectoryType<'a>>)>,
+}
+
+impl<'a, Arc: ArcLookup + ?Sized> WalkdirIter<'a, Arc> {
+    pub fn new(arc: &'a Arc, dir_info: &'a DirInfo) -> Self {
+        Self {
+            arc,
+            dirs: vec![dir_info],
+            current: None,
+            files: Vec::new(),
+        }
+    }
+
+    /// Start walking the next directory, returning false once there are none left
+    fn next_dir(&mut self) -> bool {
+        let dir = match self.dirs.pop() {
+            Some(dir) => dir,
+            None => return false,
+        };
+
+        let dir_infos = self.arc.get_dir_infos();
+        let children = self
+            .arc
+            .get_folder_child_hashes()
+            .get(dir.children_range())
+            .unwrap_or_default();
+        self.dirs.extend(
+            children
+                .iter()
+                .rev()
+                .filter_map(|child| dir_infos.get(child.index() as usize)),
+        );
+
+        // Stored in reverse order, the directory's own files are yielded first
+        if let Some(redirect) = self.redirect(dir) {
+            let range = match redirect {
+                WalkdirDirectoryType::Directory(target) => target.file_info_range(),
+                WalkdirDirectoryType::FileGroup(group) => group.range(),
+            };
+            self.files.push((self.file_infos(range), Some(redirect)));
+        }
+        self.files
+            .push((self.file_infos(dir.file_info_range()), None));
+        self.current = Some(dir);
+
+        true
+    }
+
+    /// The same as [`ArcLookup::get_directory_dependency`], but borrowing from the arc
+    fn redirect(&self, dir: &DirInfo) -> Option<WalkdirDirectoryType<'a>> {
+        if !dir.flags.redirected() {
+            return None;
+        }
+
+        let folder_offsets = self.arc.get_folder_offsets();
+        let directory_index = folder_offsets
+            .get(dir.path.index() as usize)?
+            .directory_index;
+
+        if directory_index == 0xFF_FFFF {
+            None
+        } else if dir.flags.is_symlink() {
+            let target = self.arc.get_dir_infos().get(directory_index as usize)?;
+            Some(WalkdirDirectoryType::Directory(target))
+        } else {
+            let group = folder_offsets.get(directory_index as usize)?;
+            Some(WalkdirDirectoryType::FileGroup(group))
+        }
+    }
+
+    fn file_infos(&self, range: std::ops::Range<usize>) -> slice::Iter<'a, FileInfo> {
+        self.arc
+            .get_file_infos()
+            .get(range)
+            .unwrap_or_default()
+            .iter()
+    }
+}
+
+impl<'a, Arc: ArcLookup + ?Sized> Iterator for WalkdirIter<'a, Arc> {
+    type Item = WalkdirEntry<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((files, redirect)) = self.files.last_mut() {
+                match files.next() {
+                    Some(file_info) => {
+                        return Some(WalkdirEntry {
+                            file_info,
+                            directory: self.current?,
+                            redirect: *redirect,
+                        })
+                    }
+                    None => {
+                        self.files.pop();
+                    }
+                }
+            } else if !self.next_dir() {
+                return None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arc_file::tests::test_arc;
+    use crate::filesystem::tests::hash_to_index;
+    use crate::{
+        hash40, ArcFile, ArcLookup, DirInfo, DirInfoFlags, DirectoryOffset, Hash40, LookupError,
+    };
+
+    fn dir_info(
+        path: &str,
+        folder_offset: u32,
+        children: (u32, u32),
+        flags: DirInfoFlags,
+    ) -> DirInfo {
+        DirInfo {
+            path: hash_to_index(path, folder_offset),
+            name: hash40(path.rsplit('/').next().unwrap()),
+            parent: hash40(path.rsplit_once('/').map_or("", |(parent, _)| parent)),
+            extra_dis_re: 0,
+            extra_dis_re_length: 0,
+            file_info_start_index: 0,
+            file_count: 0,
+            child_dir_start_index: children.0,
+            child_dir_count: children.1,
+            flags,
+        }
+    }
+
+    /// The test arc with `fighter` containing `fighter/mario`, along with `fighter/luigi` which is
+    /// a symlink to `fighter/mario` and `fighter/peach` which shares mario's file group
+    fn walkdir_test_arc() -> ArcFile {
+        let mut arc = test_arc();
+        let fs = &mut arc.file_system;
+
+        let redirected = DirInfoFlags::new().with_redirected(true);
+        fs.dir_infos.extend([
+            dir_info("fighter", 0xFF_FFFF, (0, 3), DirInfoFlags::new()),
+            dir_info("fighter/luigi", 1, (0, 0), redirected.with_is_symlink(true)),
+            dir_info("fighter/peach", 2, (0, 0), redirected),
+        ]);
+        fs.folder_child_hashes = vec![
+            hash_to_index("fighter/mario", 0),
+            hash_to_index("fighter/luigi", 2),
+            hash_to_index("fighter/peach", 3),
+        ];
+
+        let mario_files = fs.folder_offsets[0];
+        fs.folder_offsets.extend([
+            // fighter/luigi redirects to the DirInfo of fighter/mario
+            DirectoryOffset {
+                directory_index: 0,
+                ..mario_files
+            },
+            // fighter/peach redirects to the file group of fighter/mario
+            DirectoryOffset {
+                directory_index: 0,
+                ..mario_files
+            },
+        ]);
+
+        fs.dir_hash_to_info_index = vec![
+            hash_to_index("fighter/mario", 0),
+            hash_to_index("fighter", 1),
+            hash_to_index("fighter/luigi", 2),
+            hash_to_index("fighter/peach", 3),
+        ];
+        fs.dir_hash_to_info_index.sort_by_key(|dir| dir.hash40());
+
+        arc
+    }
+
+    fn file_path(arc: &ArcFile, entry: &super::WalkdirEntry) -> Hash40 {
+        arc.get_file_paths()[entry.file_info.file_path_index]
+            .path
+            .hash40()
+    }
+
+    #[test]
+    fn walk_directory() {
+        let arc = walkdir_test_arc();
+
+        let entries = arc.walk_dir("fighter/mario").unwrap().collect::<Vec<_>>();
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|entry| entry.redirect.is_none()));
+        assert_eq!(
+            file_path(&arc, &entries[0]),
+            hash40("fighter/mario/model.numdlb")
+        );
+        assert_eq!(
+            file_path(&arc, &entries[1]),
+            hash40("fighter/mario/alias.numdlb")
+        );
+    }
+
+    #[test]
+    fn walk_children_and_redirects() {
+        let arc = walkdir_test_arc();
+
+        let entries = arc
+            .walk_dir("fighter")
+            .unwrap()
+            .map(|entry| {
+                let redirect = match entry.redirect {
+                    None => "none",
+                    Some(super::WalkdirDirectoryType::Directory(_)) => "symlink",
+                    Some(super::WalkdirDirectoryType::FileGroup(_)) => "shared",
+                };
+
+                (entry.directory.path.hash40(), redirect)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            entries,
+            [
+                (hash40("fighter/mario"), "none"),
+                (hash40("fighter/mario"), "none"),
+                (hash40("fighter/luigi"), "symlink"),
+                (hash40("fighter/luigi"), "symlink"),
+                (hash40("fighter/peach"), "shared"),
+                (hash40("fighter/peach"), "shared"),
+            ]
+        );
+    }
+
+    #[test]
+    fn walk_missing_directory() {
+        let arc = walkdir_test_arc();
+
+        assert!(matches!(
+            arc.walk_dir("fighter/luigi/c00"),
+            Err(LookupError::Missing)
+        ));
+    }
+}
@@ -0,0 +1,174 @@
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+
+use binrw::{io::Cursor, BinReaderExt, BinResult};
+use memmap2::Mmap;
+
+use crate::{ArcFile, ArcLookup, Hash40, LookupError, Region};
+
+impl ArcFile {
+    /// Open a data.arc by memory mapping it. Reads (such as [`ArcLookup::get_file_contents`])
+    /// become copies out of the map, allowing them to happen concurrently without locking
+    /// [`ArcFile::reader`], and uncompressed files can be borrowed directly from the map using
+    /// [`ArcFile::get_file_contents_borrowed`].
+    ///
+    /// **Note:** The file must not be modified by anything else while the arc is open.
+    pub fn open_mmap<P: AsRef<Path>>(path: P) -> BinResult<Self> {
+        let file = File::open(path)?;
+
+        // Safety: the map is only ever read from, and modifying the underlying file while it is
+        // mapped is documented as unsupported above
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        let mmap = Arc::new(mmap);
+
+        let mut arc: Self = Cursor::new(&mmap[..]).read_le()?;
+        *arc.reader.get_mut().unwrap() = Box::new(BufReader::new(file));
+        arc.reader_at = Some(mmap.clone());
+        arc.mmap = Some(mmap);
+
+        Ok(arc)
+    }
+
+    /// Get the contents of a file, borrowing them from the memory map when the arc was opened
+    /// with [`ArcFile::open_mmap`] and the file is stored uncompressed. Otherwise the contents
+    /// are read the same way as [`ArcLookup::get_file_contents`].
+    pub fn get_file_contents_borrowed<Hash: Into<Hash40>>(
+        &self,
+        hash: Hash,
+        region: Region,
+    ) -> Result<Cow<'_, [u8]>, LookupError> {
+        let hash = hash.into();
+
+        if let Some(mmap) = &self.mmap {
+            let location = self.data_location(hash, region)?;
+            let start = location.offset;
+            let end = start + location.size;
+
+            if !location.compressed
+                && end <= mmap.len() as u64
+                && !self.patches.overlaps(start..end)
+            {
+                return Ok(Cow::Borrowed(&mmap[start as usize..end as usize]));
+            }
+        }
+
+        self.get_file_contents(hash, region).map(Cow::Owned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arc_file::tests::{test_arc_bytes, TEST_FILE_CONTENTS, TEST_STREAM_CONTENTS};
+    use crate::{Compression, SharedFileMode};
+    use std::path::PathBuf;
+
+    const MODEL: &str = "fighter/mario/model.numdlb";
+    const STREAM: &str = "stream:/sound/bgm/bgm_test.nus3audio";
+
+    /// Write the test arc to a temporary file, removed when dropped
+    struct TempArc(PathBuf);
+
+    impl TempArc {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "smash-arc-mmap-{}-{}.arc",
+                name,
+                std::process::id()
+            ));
+            std::fs::write(&path, test_arc_bytes()).unwrap();
+
+            Self(path)
+        }
+    }
+
+    impl Drop for TempArc {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn borrowed_contents() {
+        let file = TempArc::new("borrowed");
+        let arc = ArcFile::open_mmap(&file.0).unwrap();
+
+        let model = arc
+            .get_file_contents_borrowed(MODEL, Region::UsEnglish)
+            .unwrap();
+        let stream = arc
+            .get_file_contents_borrowed(STREAM, Region::UsEnglish)
+            .unwrap();
+
+        assert!(matches!(model, Cow::Borrowed(_)));
+        assert!(matches!(stream, Cow::Borrowed(_)));
+        assert_eq!(&model[..], TEST_FILE_CONTENTS);
+        assert_eq!(&stream[..], TEST_STREAM_CONTENTS);
+        assert_eq!(
+            arc.get_file_contents(MODEL, Region::UsEnglish).unwrap(),
+            TEST_FILE_CONTENTS
+        );
+    }
+
+    #[test]
+    fn replaced_contents() {
+        let file = TempArc::new("replaced");
+        let mut arc = ArcFile::open_mmap(&file.0).unwrap();
+
+        arc.replace_file(
+            MODEL,
+            Region::UsEnglish,
+            b"smaller",
+            Compression::Uncompressed,
+            SharedFileMode::ReplaceAll,
+        )
+        .unwrap();
+
+        let model = arc
+            .get_file_contents_borrowed(MODEL, Region::UsEnglish)
+            .unwrap();
+        assert!(matches!(model, Cow::Owned(_)));
+        assert_eq!(&model[..], b"smaller");
+
+        arc.replace_file(
+            MODEL,
+            Region::UsEnglish,
+            &[0x11; 0x1000],
+            Compression::Zstd,
+            SharedFileMode::ReplaceAll,
+        )
+        .unwrap();
+
+        assert_eq!(
+            arc.get_file_contents_borrowed(MODEL, Region::UsEnglish)
+                .unwrap(),
+            &[0x11; 0x1000][..]
+        );
+    }
+
+    #[test]
+    fn concurrent_reads() {
+        let file = TempArc::new("concurrent");
+        let arc = ArcFile::open_mmap(&file.0).unwrap();
+
+        // Holding the reader's lock proves reads don't go through it
+        let _lock = arc.reader.lock().unwrap();
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..100 {
+                        assert_eq!(
+                            arc.get_file_contents(MODEL, Region::UsEnglish).unwrap(),
+                            TEST_FILE_CONTENTS
+                        );
+                    }
+                });
+            }
+        });
+    }
+}
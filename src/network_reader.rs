@@ -1,19 +1,326 @@
-use std::io::{self, prelude::*};
-use std::net::{ToSocketAddrs, TcpStream};
+use std::convert::TryFrom;
+use std::io::{self, prelude::*, BufReader, BufWriter, SeekFrom};
+use std::net::{TcpStream, ToSocketAddrs};
 
-struct NetworkReader {
-    socket: TcpStream,
+#[cfg(doc)]
+use crate::ArcFile;
 
+/// Minimum amount of data requested from the server at once
+const READ_AHEAD_SIZE: u32 = 0x10_0000;
+
+/// A seekable reader for a file served over TCP, used by [`ArcFile::open_over_network`].
+///
+/// Seeking is done locally, and reads are cached so that small sequential reads only require a
+/// request to the server every 1 MiB.
+///
+/// ## Protocol
+///
+/// All integers are big endian.
+///
+/// 1. Upon accepting a connection, the server sends the total size of the file as a `u64`.
+/// 2. The client then sends any number of read requests, each consisting of an `offset: u64`
+///    followed by a `length: u32`.
+/// 3. The server replies to each request with a series of chunks, each consisting of a
+///    `chunk_length: u32` followed by that many bytes of data. The reply ends with a chunk of
+///    length 0. Less data than requested is only sent when the request goes past the end of the
+///    file.
+///
+/// If the server fails to read the file it closes the connection. Once a request fails the
+/// connection can't be trusted to be in sync with the server, so every later read fails too.
+pub struct NetworkReader {
+    reader: BufReader<TcpStream>,
+    writer: BufWriter<TcpStream>,
+    len: u64,
+    pos: u64,
+    cache: Vec<u8>,
+    cache_start: u64,
+    /// Whether a request failed partway through
+    broken: bool,
+}
+
+impl NetworkReader {
+    /// Connect to a server and read the size of the file it serves
+    pub fn new<Addr: ToSocketAddrs>(addr: Addr) -> io::Result<Self> {
+        let socket = TcpStream::connect(addr)?;
+        socket.set_nodelay(true)?;
+
+        let mut reader = BufReader::new(socket.try_clone()?);
+        let len = read_u64(&mut reader)?;
+
+        Ok(Self {
+            reader,
+            writer: BufWriter::new(socket),
+            len,
+            pos: 0,
+            cache: Vec::new(),
+            cache_start: 0,
+            broken: false,
+        })
+    }
+
+    /// The total size of the file being served
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Replace the cache with `length` bytes read from the server starting at `offset`. If the
+    /// request fails the cache is emptied and the connection is marked as broken.
+    fn request(&mut self, offset: u64, length: u32) -> io::Result<()> {
+        if self.broken {
+            return Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "an earlier request to the server failed",
+            ));
+        }
+
+        let result = self.try_request(offset, length);
+        if result.is_err() {
+            self.cache.clear();
+            self.cache_start = 0;
+            self.broken = true;
+        }
+
+        result
+    }
+
+    fn try_request(&mut self, offset: u64, length: u32) -> io::Result<()> {
+        self.writer.write_all(&offset.to_be_bytes())?;
+        self.writer.write_all(&length.to_be_bytes())?;
+        self.writer.flush()?;
+
+        self.cache.clear();
+        self.cache_start = offset;
+
+        loop {
+            let chunk_len = read_u32(&mut self.reader)? as usize;
+            if chunk_len == 0 {
+                break;
+            }
+
+            if self.cache.len() + chunk_len > length as usize {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "server sent more data than requested",
+                ));
+            }
+
+            let start = self.cache.len();
+            self.cache.resize(start + chunk_len, 0);
+            self.reader.read_exact(&mut self.cache[start..])?;
+        }
+
+        Ok(())
+    }
+
+    fn cached(&self) -> &[u8] {
+        let cache_end = self.cache_start + self.cache.len() as u64;
+        if (self.cache_start..cache_end).contains(&self.pos) {
+            &self.cache[(self.pos - self.cache_start) as usize..]
+        } else {
+            &[]
+        }
+    }
 }
 
 impl Seek for NetworkReader {
-    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
-        todo!()
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        };
+
+        self.pos = new_pos.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+
+        Ok(self.pos)
     }
 }
 
 impl Read for NetworkReader {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        todo!()
+        if buf.is_empty() || self.pos >= self.len {
+            return Ok(0);
+        }
+
+        if self.cached().is_empty() {
+            let remaining = self.len - self.pos;
+            let length = u32::try_from(buf.len())
+                .unwrap_or(u32::MAX)
+                .max(READ_AHEAD_SIZE)
+                .min(u32::try_from(remaining).unwrap_or(u32::MAX));
+
+            self.request(self.pos, length)?;
+
+            if self.cache.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "server sent no data before the end of the file",
+                ));
+            }
+        }
+
+        let cached = self.cached();
+        let len = cached.len().min(buf.len());
+        buf[..len].copy_from_slice(&cached[..len]);
+        self.pos += len as u64;
+
+        Ok(len)
+    }
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut buf = [0; 8];
+    reader.read_exact(&mut buf)?;
+
+    Ok(u64::from_be_bytes(buf))
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)?;
+
+    Ok(u32::from_be_bytes(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+    use std::net::{SocketAddr, TcpListener};
+    use std::thread;
+
+    const CHUNK_SIZE: usize = 0x1000;
+
+    /// Serve `data` to a single client on a loopback port, following the protocol
+    fn serve(data: Vec<u8>) -> SocketAddr {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            socket
+                .write_all(&(data.len() as u64).to_be_bytes())
+                .unwrap();
+
+            let mut request = [0; 12];
+            while socket.read_exact(&mut request).is_ok() {
+                let offset = u64::from_be_bytes(request[..8].try_into().unwrap()) as usize;
+                let length = u32::from_be_bytes(request[8..].try_into().unwrap()) as usize;
+
+                let start = offset.min(data.len());
+                let end = (offset + length).min(data.len());
+                for chunk in data[start..end].chunks(CHUNK_SIZE) {
+                    socket
+                        .write_all(&(chunk.len() as u32).to_be_bytes())
+                        .unwrap();
+                    socket.write_all(chunk).unwrap();
+                }
+                socket.write_all(&0u32.to_be_bytes()).unwrap();
+            }
+        });
+
+        addr
+    }
+
+    fn test_data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn read_whole_file() {
+        let data = test_data(READ_AHEAD_SIZE as usize * 2 + 0x123);
+        let mut reader = NetworkReader::new(serve(data.clone())).unwrap();
+
+        assert_eq!(reader.len(), data.len() as u64);
+
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).unwrap();
+
+        assert_eq!(read, data);
+    }
+
+    #[test]
+    fn seek_and_read() {
+        let data = test_data(0x10000);
+        let mut reader = NetworkReader::new(serve(data.clone())).unwrap();
+
+        let mut buf = [0; 0x20];
+        for &pos in &[
+            SeekFrom::Start(0x8000),
+            SeekFrom::Current(-0x40),
+            SeekFrom::End(-0x20),
+            SeekFrom::Start(0x10),
+        ] {
+            let offset = reader.seek(pos).unwrap() as usize;
+            reader.read_exact(&mut buf).unwrap();
+
+            assert_eq!(buf, data[offset..offset + buf.len()]);
+        }
+
+        reader.seek(SeekFrom::End(0)).unwrap();
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+        assert!(reader.seek(SeekFrom::Current(-0x20000)).is_err());
+    }
+
+    #[test]
+    fn open_arc_over_network() {
+        use crate::arc_file::tests::{test_arc_bytes, TEST_FILE_CONTENTS};
+        use crate::{ArcFile, ArcLookup, Region};
+
+        let arc = ArcFile::open_over_network(serve(test_arc_bytes())).unwrap();
+
+        assert_eq!(
+            arc.get_file_contents("fighter/mario/model.numdlb", Region::UsEnglish)
+                .unwrap(),
+            TEST_FILE_CONTENTS
+        );
+    }
+
+    #[test]
+    fn failed_request() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // Reply to the first request with a chunk cut short, then close the connection
+        thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            socket.write_all(&0x100u64.to_be_bytes()).unwrap();
+
+            let mut request = [0; 12];
+            socket.read_exact(&mut request).unwrap();
+            socket.write_all(&0x80u32.to_be_bytes()).unwrap();
+            socket.write_all(&[0x55; 0x10]).unwrap();
+        });
+
+        let mut reader = NetworkReader::new(addr).unwrap();
+        let mut buf = [0; 0x10];
+        assert!(reader.read(&mut buf).is_err());
+        assert!(reader.cache.is_empty());
+
+        reader.seek(SeekFrom::Start(0)).unwrap();
+        assert_eq!(
+            reader.read(&mut buf).unwrap_err().kind(),
+            io::ErrorKind::BrokenPipe
+        );
+    }
+
+    #[test]
+    fn read_past_end() {
+        let data = test_data(0x100);
+        let mut reader = NetworkReader::new(serve(data.clone())).unwrap();
+
+        reader.seek(SeekFrom::Start(0xF0)).unwrap();
+
+        let mut buf = [0; 0x20];
+        assert!(reader.read_exact(&mut buf).is_err());
     }
 }
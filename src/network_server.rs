@@ -0,0 +1,198 @@
+use std::io::{self, prelude::*, BufReader, BufWriter, SeekFrom};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::SeekRead;
+
+/// The port [`ArcFile::open_over_network`](crate::ArcFile::open_over_network) is typically used
+/// with
+pub const DEFAULT_PORT: u16 = 43022;
+
+/// Maximum size of a chunk of data sent in reply to a read request
+const CHUNK_SIZE: usize = 0x10000;
+
+/// Serves a reader (typically a data.arc dump) over TCP to any number of concurrent
+/// [`NetworkReader`](crate::NetworkReader)s, using the protocol described there.
+///
+/// ```no_run
+/// use smash_arc::{NetworkServer, DEFAULT_PORT};
+/// use std::fs::File;
+///
+/// let server = NetworkServer::bind(File::open("data.arc")?, ("0.0.0.0", DEFAULT_PORT))?;
+/// server.serve()?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct NetworkServer<R: SeekRead + Send + 'static> {
+    listener: TcpListener,
+    reader: Arc<Mutex<R>>,
+    len: u64,
+}
+
+impl<R: SeekRead + Send + 'static> NetworkServer<R> {
+    /// Create a server for `reader` listening on the given address
+    pub fn bind<Addr: ToSocketAddrs>(mut reader: R, addr: Addr) -> io::Result<Self> {
+        let len = reader.seek(SeekFrom::End(0))?;
+
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+            reader: Arc::new(Mutex::new(reader)),
+            len,
+        })
+    }
+
+    /// The address the server is listening on
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accept connections forever, serving each client on its own thread. Errors from
+    /// individual clients only close that client's connection, and connections which fail before
+    /// being accepted are skipped. Any other error accepting connections (such as running out of
+    /// file descriptors) stops the server and is returned.
+    pub fn serve(self) -> io::Result<()> {
+        for socket in self.listener.incoming() {
+            let socket = match socket {
+                Ok(socket) => socket,
+                Err(err) if is_connection_error(&err) => continue,
+                Err(err) => return Err(err),
+            };
+            let reader = Arc::clone(&self.reader);
+            let len = self.len;
+
+            thread::spawn(move || {
+                let _ = serve_client(socket, &reader, len);
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Start serving on a background thread, returning the address being listened on. An error
+    /// which stops the server is discarded, call [`NetworkServer::serve`] on a thread of your own
+    /// to handle it.
+    pub fn spawn(self) -> io::Result<SocketAddr> {
+        let addr = self.local_addr()?;
+        thread::spawn(move || self.serve());
+
+        Ok(addr)
+    }
+}
+
+/// Whether an error accepting a connection only affects that connection, rather than the
+/// listener as a whole
+fn is_connection_error(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::Interrupted
+    )
+}
+
+fn serve_client<R: SeekRead>(socket: TcpStream, reader: &Mutex<R>, len: u64) -> io::Result<()> {
+    socket.set_nodelay(true)?;
+
+    let mut requests = BufReader::new(socket.try_clone()?);
+    let mut writer = BufWriter::new(socket);
+
+    writer.write_all(&len.to_be_bytes())?;
+    writer.flush()?;
+
+    let mut chunk = vec![0; CHUNK_SIZE];
+    let mut request = [0; 12];
+    loop {
+        match requests.read_exact(&mut request) {
+            Ok(()) => (),
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(err) => return Err(err),
+        }
+
+        let mut offset = u64::from_be_bytes([
+            request[0], request[1], request[2], request[3], request[4], request[5], request[6],
+            request[7],
+        ]);
+        let length = u32::from_be_bytes([request[8], request[9], request[10], request[11]]);
+
+        let end = offset.saturating_add(length as u64).min(len);
+        while offset < end {
+            let chunk_len = ((end - offset) as usize).min(CHUNK_SIZE);
+            {
+                let mut reader = reader.lock().unwrap();
+                reader.seek(SeekFrom::Start(offset))?;
+                reader.read_exact(&mut chunk[..chunk_len])?;
+            }
+
+            writer.write_all(&(chunk_len as u32).to_be_bytes())?;
+            writer.write_all(&chunk[..chunk_len])?;
+            offset += chunk_len as u64;
+        }
+
+        writer.write_all(&0u32.to_be_bytes())?;
+        writer.flush()?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NetworkReader;
+    use binrw::io::Cursor;
+
+    fn test_data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn spawn_server(data: Vec<u8>) -> SocketAddr {
+        NetworkServer::bind(Cursor::new(data), ("127.0.0.1", 0))
+            .unwrap()
+            .spawn()
+            .unwrap()
+    }
+
+    #[test]
+    fn serve_ranges() {
+        let data = test_data(CHUNK_SIZE * 3 + 0x10);
+        let mut reader = NetworkReader::new(spawn_server(data.clone())).unwrap();
+
+        assert_eq!(reader.len(), data.len() as u64);
+
+        let mut buf = vec![0; CHUNK_SIZE + 0x20];
+        for &offset in &[0, 0x1234, CHUNK_SIZE * 2 - 0x10] {
+            reader.seek(SeekFrom::Start(offset as u64)).unwrap();
+            reader.read_exact(&mut buf).unwrap();
+
+            assert_eq!(buf, data[offset..offset + buf.len()]);
+        }
+
+        reader.seek(SeekFrom::End(-0x10)).unwrap();
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).unwrap();
+        assert_eq!(read, data[data.len() - 0x10..]);
+    }
+
+    #[test]
+    fn serve_concurrent_clients() {
+        let data = test_data(0x4_0000);
+        let addr = spawn_server(data.clone());
+
+        let clients: Vec<_> = (0..4)
+            .map(|client| {
+                let data = data.clone();
+                thread::spawn(move || {
+                    let mut reader = NetworkReader::new(addr).unwrap();
+                    let offset = client * 0x1_0000 + 0x100;
+                    reader.seek(SeekFrom::Start(offset as u64)).unwrap();
+
+                    let mut buf = [0; 0x800];
+                    reader.read_exact(&mut buf).unwrap();
+                    assert_eq!(buf[..], data[offset..offset + buf.len()]);
+                })
+            })
+            .collect();
+
+        for client in clients {
+            client.join().unwrap();
+        }
+    }
+}
@@ -0,0 +1,311 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// A reader which reads from a given offset rather than from a current position. Unlike
+/// [`SeekRead`](crate::SeekRead) this only requires `&self`, so a single reader can be shared
+/// between threads without locking (where the underlying reader allows it).
+pub trait ReadAt {
+    /// Read bytes starting at `offset` into `buf`, returning the number of bytes read. A return
+    /// value of 0 means `offset` is at or past the end of the data.
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize>;
+
+    /// Read exactly enough bytes to fill `buf`, starting at `offset`
+    fn read_exact_at(&self, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+        while !buf.is_empty() {
+            match self.read_at(buf, offset) {
+                Ok(0) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "failed to fill whole buffer",
+                    ))
+                }
+                Ok(read) => {
+                    buf = &mut buf[read..];
+                    offset += read as u64;
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The length of the data. By default this is found by searching for the first offset at
+    /// which nothing can be read, implement this if the length is known.
+    fn size(&self) -> io::Result<u64> {
+        let has_data = |offset| self.read_at(&mut [0], offset).map(|read| read != 0);
+
+        if !has_data(0)? {
+            return Ok(0);
+        }
+
+        // The end is after `low` and at or before `high`
+        let mut low = 0;
+        let mut high = 1;
+        while has_data(high)? {
+            low = high;
+            high = high.saturating_mul(2);
+        }
+
+        while high - low > 1 {
+            let mid = low + (high - low) / 2;
+            if has_data(mid)? {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+
+        Ok(high)
+    }
+}
+
+impl ReadAt for [u8] {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        let start = offset.min(self.len() as u64) as usize;
+        let len = buf.len().min(self.len() - start);
+        buf[..len].copy_from_slice(&self[start..start + len]);
+
+        Ok(len)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.len() as u64)
+    }
+}
+
+impl ReadAt for Vec<u8> {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        self[..].read_at(buf, offset)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.len() as u64)
+    }
+}
+
+impl<T: ReadAt + ?Sized> ReadAt for &T {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        (**self).read_at(buf, offset)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        (**self).size()
+    }
+}
+
+impl<T: ReadAt + ?Sized> ReadAt for Box<T> {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        (**self).read_at(buf, offset)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        (**self).size()
+    }
+}
+
+impl<T: ReadAt + ?Sized> ReadAt for Arc<T> {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        (**self).read_at(buf, offset)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        (**self).size()
+    }
+}
+
+#[cfg(unix)]
+impl ReadAt for File {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        std::os::unix::fs::FileExt::read_at(self, buf, offset)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.metadata()?.len())
+    }
+}
+
+#[cfg(windows)]
+impl ReadAt for File {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        std::os::windows::fs::FileExt::seek_read(self, buf, offset)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.metadata()?.len())
+    }
+}
+
+/// A file opened for positional reads. Only unix and windows support positional reads of files,
+/// so on other targets (such as the Switch) the file is locked and seeked for each read instead.
+#[cfg(any(unix, windows))]
+pub(crate) type ReadAtFile = File;
+#[cfg(not(any(unix, windows)))]
+pub(crate) type ReadAtFile = Mutex<File>;
+
+/// Open a file for positional reads, see [`ReadAtFile`]
+pub(crate) fn open_read_at<P: AsRef<Path>>(path: P) -> io::Result<ReadAtFile> {
+    let file = File::open(path)?;
+    #[cfg(not(any(unix, windows)))]
+    let file = Mutex::new(file);
+
+    Ok(file)
+}
+
+#[cfg(feature = "mmap")]
+impl ReadAt for memmap2::Mmap {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        self[..].read_at(buf, offset)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.len() as u64)
+    }
+}
+
+/// Any [`SeekRead`](crate::SeekRead) can be used for positional reads by locking it for the
+/// duration of each read
+impl<R: Read + Seek + ?Sized> ReadAt for Mutex<R> {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        let mut reader = self.lock().unwrap();
+        reader.seek(SeekFrom::Start(offset))?;
+
+        reader.read(buf)
+    }
+
+    fn read_exact_at(&self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        let mut reader = self.lock().unwrap();
+        reader.seek(SeekFrom::Start(offset))?;
+
+        reader.read_exact(buf)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        self.lock().unwrap().seek(SeekFrom::End(0))
+    }
+}
+
+/// Adapts a [`ReadAt`] into a [`Read`] + [`Seek`] by keeping track of the current position
+pub(crate) struct ReadAtCursor<R: ReadAt> {
+    inner: R,
+    pos: u64,
+}
+
+impl<R: ReadAt> ReadAtCursor<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        Self { inner, pos: 0 }
+    }
+}
+
+impl<R: ReadAt> Read for ReadAtCursor<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read_at(buf, self.pos)?;
+        self.pos += read as u64;
+
+        Ok(read)
+    }
+}
+
+impl<R: ReadAt> Seek for ReadAtCursor<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+            SeekFrom::End(offset) => self.inner.size()?.checked_add_signed(offset),
+        };
+
+        self.pos = new_pos.ok_or_else(invalid_seek)?;
+
+        Ok(self.pos)
+    }
+}
+
+fn invalid_seek() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "invalid seek to a negative or overflowing position",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use binrw::io::Cursor;
+
+    fn test_data() -> Vec<u8> {
+        (0..=255).collect()
+    }
+
+    #[test]
+    fn slice_read_at() {
+        let data = test_data();
+        let mut buf = [0; 0x10];
+
+        assert_eq!(data.read_at(&mut buf, 0x20).unwrap(), 0x10);
+        assert_eq!(buf[..], data[0x20..0x30]);
+        assert_eq!(data.read_at(&mut buf, 0xF8).unwrap(), 8);
+        assert_eq!(data.read_at(&mut buf, 0x1000).unwrap(), 0);
+        assert!(data.read_exact_at(&mut buf, 0xF8).is_err());
+    }
+
+    #[test]
+    fn mutex_read_at() {
+        let reader = Mutex::new(Cursor::new(test_data()));
+        let mut buf = [0; 0x10];
+
+        reader.read_exact_at(&mut buf, 0x80).unwrap();
+        assert_eq!(buf[..], test_data()[0x80..0x90]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_read_at() {
+        let path = std::env::temp_dir().join(format!("smash-arc-read-at-{}", std::process::id()));
+        std::fs::write(&path, test_data()).unwrap();
+
+        let file = File::open(&path).unwrap();
+        let mut buf = [0; 0x10];
+        file.read_exact_at(&mut buf, 0x40).unwrap();
+        assert_eq!(buf[..], test_data()[0x40..0x50]);
+        assert_eq!(file.size().unwrap(), 0x100);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn cursor_read() {
+        let mut cursor = ReadAtCursor::new(test_data());
+        cursor.seek(SeekFrom::Start(0x10)).unwrap();
+        cursor.seek(SeekFrom::Current(0xE0)).unwrap();
+
+        let mut read = Vec::new();
+        cursor.read_to_end(&mut read).unwrap();
+        assert_eq!(read, test_data()[0xF0..]);
+
+        assert_eq!(cursor.seek(SeekFrom::End(-0x10)).unwrap(), 0xF0);
+        assert_eq!(cursor.seek(SeekFrom::End(0)).unwrap(), 0x100);
+        assert!(cursor.seek(SeekFrom::End(-0x101)).is_err());
+    }
+
+    /// Only implements [`ReadAt::read_at`], so the default [`ReadAt::size`] is used
+    struct Unsized(Vec<u8>);
+
+    impl ReadAt for Unsized {
+        fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+            self.0.read_at(buf, offset)
+        }
+    }
+
+    #[test]
+    fn default_size() {
+        for len in [0, 1, 2, 0x100, 0x1234] {
+            assert_eq!(Unsized(vec![0; len]).size().unwrap(), len as u64);
+        }
+
+        let reader = Mutex::new(Cursor::new(test_data()));
+        assert_eq!(reader.size().unwrap(), 0x100);
+    }
+}
@@ -0,0 +1,549 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::{
+    ArcFile, ArcLookup, FileData, FileDataFlags, FileDataIdx, Hash40, LookupError, ReadAt, Region,
+    SeekRead,
+};
+
+/// Alignment of file data appended to the end of the data section
+const APPENDED_DATA_ALIGNMENT: u64 = 0x10;
+
+/// How data passed to [`ArcFile::replace_file`] should be stored in the arc
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Uncompressed,
+    /// Compress the data using zstd, unless that wouldn't make it any smaller, in which case it
+    /// is stored uncompressed.
+    ///
+    /// **Note:** The `rust-zstd` backend can only decompress, so with it data is always stored
+    /// uncompressed.
+    Zstd,
+}
+
+/// What to do when the file passed to [`ArcFile::replace_file`] shares its data with other files
+/// (see [`ArcLookup::get_shared_files`])
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SharedFileMode {
+    /// Give the file its own copy of the data, leaving every other file sharing it untouched
+    Unshare,
+    /// Replace the shared data, changing the contents of every file sharing it
+    ReplaceAll,
+}
+
+/// File data which has been replaced in memory but not yet written out, keyed by absolute
+/// offset in the arc. Patches either overwrite the original data section or are appended past
+/// the end of it.
+#[derive(Default)]
+pub(crate) struct DataPatches {
+    patches: BTreeMap<u64, Vec<u8>>,
+    end: u64,
+}
+
+impl DataPatches {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.patches.is_empty()
+    }
+
+    /// The end of the appended data, or 0 if nothing has been appended
+    pub(crate) fn end(&self) -> u64 {
+        self.end
+    }
+
+    pub(crate) fn insert(&mut self, offset: u64, data: Vec<u8>) {
+        self.end = self.end.max(offset + data.len() as u64);
+        self.patches.insert(offset, data);
+    }
+
+    /// Whether any patch overlaps the given range of offsets
+    #[cfg(feature = "mmap")]
+    pub(crate) fn overlaps(&self, range: std::ops::Range<u64>) -> bool {
+        self.patches
+            .range(..range.end)
+            .next_back()
+            .is_some_and(|(&start, data)| range.start < start + data.len() as u64)
+    }
+
+    fn find(&self, pos: u64) -> Option<(u64, &[u8])> {
+        self.patches
+            .range(..=pos)
+            .next_back()
+            .filter(|(&start, data)| pos < start + data.len() as u64)
+            .map(|(&start, data)| (start, &data[..]))
+    }
+
+    fn next_start(&self, pos: u64) -> Option<u64> {
+        self.patches.range(pos..).next().map(|(&start, _)| start)
+    }
+}
+
+/// A reader over the original arc with [`DataPatches`] applied on top of it. Past the end of
+/// the original data section, space between appended patches reads as zeroes.
+pub(crate) struct PatchedReader<'a, R: SeekRead> {
+    inner: R,
+    inner_pos: Option<u64>,
+    patches: &'a DataPatches,
+    data_end: u64,
+    pos: u64,
+}
+
+impl<'a, R: SeekRead> PatchedReader<'a, R> {
+    pub(crate) fn new(inner: R, patches: &'a DataPatches, data_end: u64) -> Self {
+        Self {
+            inner,
+            inner_pos: None,
+            patches,
+            data_end,
+            pos: 0,
+        }
+    }
+}
+
+impl<'a, R: SeekRead> Read for PatchedReader<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let inner = &mut self.inner;
+        let inner_pos = &mut self.inner_pos;
+
+        let read = read_patched(self.patches, self.data_end, buf, self.pos, |buf, pos| {
+            if *inner_pos != Some(pos) {
+                inner.seek(SeekFrom::Start(pos))?;
+            }
+
+            let read = inner.read(buf)?;
+            *inner_pos = Some(pos + read as u64);
+
+            Ok(read)
+        })?;
+
+        self.pos += read as u64;
+
+        Ok(read)
+    }
+}
+
+/// The positional version of [`PatchedReader`]
+pub(crate) struct PatchedReadAt<'a, R: ReadAt> {
+    inner: R,
+    patches: &'a DataPatches,
+    data_end: u64,
+}
+
+impl<'a, R: ReadAt> ReadAt for PatchedReadAt<'a, R> {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        read_patched(self.patches, self.data_end, buf, offset, |buf, pos| {
+            self.inner.read_at(buf, pos)
+        })
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.inner.size()?.max(self.patches.end()))
+    }
+}
+
+/// Read from `pos` with `patches` applied, using `read_inner` to read from the original arc.
+/// Each call reads from at most one patch or one unpatched range.
+fn read_patched<F>(
+    patches: &DataPatches,
+    data_end: u64,
+    buf: &mut [u8],
+    pos: u64,
+    read_inner: F,
+) -> io::Result<usize>
+where
+    F: FnOnce(&mut [u8], u64) -> io::Result<usize>,
+{
+    if let Some((start, data)) = patches.find(pos) {
+        let data = &data[(pos - start) as usize..];
+        let len = data.len().min(buf.len());
+        buf[..len].copy_from_slice(&data[..len]);
+
+        return Ok(len);
+    }
+
+    let section_end = if pos < data_end {
+        data_end
+    } else {
+        patches.end()
+    };
+    let limit = patches
+        .next_start(pos)
+        .map_or(section_end, |next| next.min(section_end));
+    let len = limit.saturating_sub(pos).min(buf.len() as u64) as usize;
+
+    if pos < data_end {
+        read_inner(&mut buf[..len], pos)
+    } else {
+        buf[..len].iter_mut().for_each(|byte| *byte = 0);
+
+        Ok(len)
+    }
+}
+
+impl<'a, R: SeekRead> Seek for PatchedReader<'a, R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.pos = match pos {
+            SeekFrom::Start(pos) => pos,
+            SeekFrom::Current(offset) => self
+                .pos
+                .checked_add_signed(offset)
+                .ok_or_else(invalid_seek)?,
+            SeekFrom::End(offset) => {
+                // Appended patches may extend past the end of the original arc
+                let base_len = self.inner.seek(SeekFrom::End(0))?;
+                self.inner_pos = Some(base_len);
+
+                base_len
+                    .max(self.patches.end())
+                    .checked_add_signed(offset)
+                    .ok_or_else(invalid_seek)?
+            }
+        };
+
+        Ok(self.pos)
+    }
+}
+
+fn invalid_seek() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "invalid seek to a negative or overflowing position",
+    )
+}
+
+impl ArcFile {
+    /// Apply any replaced data on top of a reader over the original arc
+    pub(crate) fn patch_reader<'a, R: SeekRead + 'a>(
+        &'a self,
+        reader: R,
+    ) -> Box<dyn SeekRead + 'a> {
+        if self.patches.is_empty() {
+            Box::new(reader)
+        } else {
+            Box::new(PatchedReader::new(reader, &self.patches, self.data_end()))
+        }
+    }
+
+    /// Apply any replaced data on top of a positional reader over the original arc
+    pub(crate) fn patch_read_at<'a, R: ReadAt + 'a>(&'a self, reader: R) -> Box<dyn ReadAt + 'a> {
+        if self.patches.is_empty() {
+            Box::new(reader)
+        } else {
+            Box::new(PatchedReadAt {
+                inner: reader,
+                patches: &self.patches,
+                data_end: self.data_end(),
+            })
+        }
+    }
+
+    /// Offset of the end of the original data section, before any appended data
+    pub(crate) fn data_end(&self) -> u64 {
+        self.file_system_offset.min(self.search_file_system_offset)
+    }
+
+    /// Replace the contents of a (non-stream) file for a given region.
+    ///
+    /// If the new data fits in the space used by the previous data it is stored in place,
+    /// otherwise it is appended after the end of the data section. The data is kept in memory
+    /// and is readable through the usual [`ArcLookup`] methods until the arc is written out using
+    /// [`ArcFile::write_to`]. The folder containing the file is grown to cover the new data, so
+    /// the folder can still be loaded as a whole.
+    ///
+    /// `shared` decides what happens when the file shares its data with other files. Files which
+    /// are unshared are always given newly appended data.
+    pub fn replace_file<Hash: Into<Hash40>>(
+        &mut self,
+        hash: Hash,
+        region: Region,
+        data: &[u8],
+        compression: Compression,
+        shared: SharedFileMode,
+    ) -> Result<(), LookupError> {
+        let file_info = *self.get_file_info_from_hash(hash.into())?;
+        let file_in_folder = self.get_file_in_folder(&file_info, region);
+        let file_data_index = file_in_folder.file_data_index;
+        let old_data = self.get_file_datas()[file_data_index];
+
+        let folder_index = file_in_folder.folder_offset_index as usize;
+        let folder_start =
+            self.file_section_offset + self.get_folder_offsets()[folder_index].offset;
+
+        let (stored, flags) = compression.encode(data, old_data.flags)?;
+
+        let comp_size = u32::try_from(stored.len()).map_err(|_| too_large())?;
+        let decomp_size = u32::try_from(data.len()).map_err(|_| too_large())?;
+
+        let is_aliased = self
+            .get_file_info_to_datas()
+            .iter()
+            .filter(|info_to_data| info_to_data.file_data_index == file_data_index)
+            .nth(1)
+            .is_some();
+        let unshare = is_aliased && shared == SharedFileMode::Unshare;
+
+        let offset_in_folder = if !unshare && comp_size <= old_data.comp_size {
+            old_data.offset_in_folder
+        } else {
+            self.next_append_offset(folder_start)?
+        };
+
+        self.patches.insert(
+            folder_start + ((offset_in_folder as u64) << 2),
+            stored.into_owned(),
+        );
+
+        // Grow the folder to cover the new data, so loading the folder as a whole reads all of it
+        let mut folder = self.get_folder_offsets()[folder_index];
+        let data_end = ((offset_in_folder as u64) << 2) + comp_size as u64;
+        folder.size = folder
+            .size
+            .max(u32::try_from(data_end).map_err(|_| too_large())?);
+        folder.decomp_size = folder
+            .decomp_size
+            .saturating_add(decomp_size.saturating_sub(old_data.decomp_size));
+        self.get_folder_offsets_mut()[folder_index] = folder;
+
+        let new_data = FileData {
+            offset_in_folder,
+            comp_size,
+            decomp_size,
+            flags,
+        };
+
+        if unshare {
+            let fs = &mut self.file_system;
+            fs.file_datas.push(new_data);
+            fs.fs_header.extra_sub_count += 1;
+
+            let new_index = FileDataIdx::from(fs.file_datas.len() - 1);
+            self.get_file_in_folder_mut(&file_info, region)
+                .file_data_index = new_index;
+        } else {
+            self.get_file_datas_mut()[file_data_index] = new_data;
+        }
+
+        Ok(())
+    }
+
+    /// The `offset_in_folder` at which the next file data can be appended for a folder starting
+    /// at the absolute offset `folder_start`
+    pub(crate) fn next_append_offset(&self, folder_start: u64) -> Result<u32, LookupError> {
+        let append_start = self.data_end().max(self.patches.end());
+        let relative = (append_start - folder_start + APPENDED_DATA_ALIGNMENT - 1)
+            & !(APPENDED_DATA_ALIGNMENT - 1);
+
+        u32::try_from(relative >> 2).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "appended file data is out of range of its folder offset",
+            )
+            .into()
+        })
+    }
+}
+
+impl Compression {
+    /// Encode file data for storage, returning it along with `flags` updated to match
+    pub(crate) fn encode(
+        self,
+        data: &[u8],
+        flags: FileDataFlags,
+    ) -> io::Result<(Cow<'_, [u8]>, FileDataFlags)> {
+        Ok(match self {
+            Compression::Uncompressed => (
+                Cow::Borrowed(data),
+                flags.with_compressed(false).with_use_zstd(false),
+            ),
+            Compression::Zstd => {
+                let compressed = crate::zstd_backend::encode_all(data)?;
+                if compressed.len() >= data.len() {
+                    return Compression::Uncompressed.encode(data, flags);
+                }
+
+                (
+                    Cow::Owned(compressed),
+                    flags.with_compressed(true).with_use_zstd(true),
+                )
+            }
+        })
+    }
+}
+
+pub(crate) fn too_large() -> LookupError {
+    io::Error::new(io::ErrorKind::InvalidInput, "file data is too large").into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arc_file::tests::{contents, rewrite, test_arc, TEST_FILE_CONTENTS};
+    use binrw::io::Cursor;
+
+    const MODEL: &str = "fighter/mario/model.numdlb";
+    const ALIAS: &str = "fighter/mario/alias.numdlb";
+
+    #[test]
+    fn replace_in_place() {
+        let mut arc = test_arc();
+        arc.replace_file(
+            MODEL,
+            Region::UsEnglish,
+            b"smaller",
+            Compression::Uncompressed,
+            SharedFileMode::ReplaceAll,
+        )
+        .unwrap();
+
+        assert_eq!(arc.get_file_datas()[0].offset_in_folder, 0);
+        assert_eq!(contents(&arc, MODEL), b"smaller");
+        assert_eq!(contents(&arc, ALIAS), b"smaller");
+
+        let arc = rewrite(&arc);
+        assert_eq!(arc.data_end(), test_arc().data_end());
+        assert_eq!(contents(&arc, MODEL), b"smaller");
+    }
+
+    #[test]
+    fn replace_appended() {
+        let data = vec![0x55; 0x100];
+
+        let mut arc = test_arc();
+        arc.replace_file(
+            MODEL,
+            Region::UsEnglish,
+            &data,
+            Compression::Uncompressed,
+            SharedFileMode::ReplaceAll,
+        )
+        .unwrap();
+
+        assert_eq!(contents(&arc, MODEL), data);
+
+        let arc = rewrite(&arc);
+        assert_eq!(contents(&arc, MODEL), data);
+        assert_eq!(contents(&arc, ALIAS), data);
+        assert!(
+            arc.get_file_offset_from_hash(MODEL.into(), Region::UsEnglish)
+                .unwrap()
+                >= 0x300
+        );
+
+        // The folder grows to cover the appended data
+        let folder = arc.get_folder_offsets()[0];
+        let offset_in_folder = (arc.get_file_datas()[0].offset_in_folder as u64) << 2;
+        assert_eq!({ folder.size } as u64, offset_in_folder + 0x100);
+        assert_eq!({ folder.decomp_size }, 0x100);
+    }
+
+    #[test]
+    fn replace_compressed() {
+        let data = vec![0x55; 0x1000];
+
+        let mut arc = test_arc();
+        arc.replace_file(
+            MODEL,
+            Region::UsEnglish,
+            &data,
+            Compression::Zstd,
+            SharedFileMode::ReplaceAll,
+        )
+        .unwrap();
+
+        let arc = rewrite(&arc);
+        let file_data = arc.get_file_datas()[0];
+
+        // rust-zstd can't compress, so the data is stored as-is
+        let compressed = cfg!(not(feature = "rust-zstd"));
+        assert_eq!(file_data.flags.compressed(), compressed);
+        assert_eq!(file_data.flags.use_zstd(), compressed);
+        assert_eq!(file_data.decomp_size, 0x1000);
+        assert_eq!(contents(&arc, MODEL), data);
+    }
+
+    #[test]
+    fn replace_incompressible() {
+        let mut arc = test_arc();
+        arc.replace_file(
+            MODEL,
+            Region::UsEnglish,
+            b"tiny",
+            Compression::Zstd,
+            SharedFileMode::ReplaceAll,
+        )
+        .unwrap();
+
+        let file_data = arc.get_file_datas()[0];
+        assert!(!file_data.flags.compressed() && !file_data.flags.use_zstd());
+        assert_eq!(file_data.comp_size, 4);
+        assert_eq!(contents(&rewrite(&arc), MODEL), b"tiny");
+    }
+
+    #[test]
+    fn replace_unshared() {
+        let mut arc = test_arc();
+        arc.replace_file(
+            MODEL,
+            Region::UsEnglish,
+            b"smaller",
+            Compression::Uncompressed,
+            SharedFileMode::Unshare,
+        )
+        .unwrap();
+
+        let arc = rewrite(&arc);
+
+        assert_eq!(arc.get_file_datas().len(), 2);
+        assert_eq!(contents(&arc, MODEL), b"smaller");
+        assert_eq!(contents(&arc, ALIAS), TEST_FILE_CONTENTS);
+    }
+
+    #[test]
+    fn replace_twice() {
+        let mut arc = test_arc();
+        for data in [&[0x11; 0x40][..], &[0x22; 0x80][..], &[0x33; 0x20][..]] {
+            arc.replace_file(
+                MODEL,
+                Region::UsEnglish,
+                data,
+                Compression::Uncompressed,
+                SharedFileMode::Unshare,
+            )
+            .unwrap();
+
+            assert_eq!(contents(&arc, MODEL), data);
+            assert_eq!(contents(&rewrite(&arc), MODEL), data);
+        }
+
+        assert_eq!(contents(&arc, ALIAS), TEST_FILE_CONTENTS);
+    }
+
+    #[test]
+    fn patched_seek_from_end() {
+        let mut patches = DataPatches::default();
+        patches.insert(0x20, vec![0x55; 0x10]);
+
+        let mut reader = PatchedReader::new(Cursor::new(vec![0x11; 0x10]), &patches, 0x10);
+        assert_eq!(reader.seek(SeekFrom::End(0)).unwrap(), 0x30);
+        assert_eq!(reader.seek(SeekFrom::End(-8)).unwrap(), 0x28);
+
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).unwrap();
+        assert_eq!(read, [0x55; 8]);
+        assert!(reader.seek(SeekFrom::End(-0x31)).is_err());
+    }
+
+    #[test]
+    fn patched_seek_from_current() {
+        let patches = DataPatches::default();
+
+        let mut reader = PatchedReader::new(Cursor::new(vec![0x11; 0x10]), &patches, 0x10);
+        assert_eq!(reader.seek(SeekFrom::Current(8)).unwrap(), 8);
+        assert_eq!(reader.seek(SeekFrom::Current(-4)).unwrap(), 4);
+        assert!(reader.seek(SeekFrom::Current(-5)).is_err());
+
+        reader.seek(SeekFrom::Start(u64::MAX)).unwrap();
+        assert!(reader.seek(SeekFrom::Current(1)).is_err());
+    }
+}
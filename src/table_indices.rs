@@ -1,7 +1,7 @@
-use binrw::BinRead;
+use binrw::{BinRead, BinWrite};
 
 #[repr(transparent)]
-#[derive(BinRead, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(BinRead, BinWrite, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct FilePathIdx(pub u32);
 
 impl From<FilePathIdx> for usize {
@@ -23,7 +23,7 @@ impl From<usize> for FilePathIdx {
 }
 
 #[repr(transparent)]
-#[derive(BinRead, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(BinRead, BinWrite, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct FileInfoIdx(pub u32);
 
 impl From<FileInfoIdx> for usize {
@@ -51,7 +51,7 @@ impl From<usize> for FileInfoIdx {
 }
 
 #[repr(transparent)]
-#[derive(BinRead, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(BinRead, BinWrite, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct FileInfoIndiceIdx(pub u32);
 
 impl From<FileInfoIndiceIdx> for usize {
@@ -79,7 +79,7 @@ impl From<usize> for FileInfoIndiceIdx {
 }
 
 #[repr(transparent)]
-#[derive(BinRead, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(BinRead, BinWrite, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct InfoToDataIdx(pub u32);
 
 impl From<InfoToDataIdx> for usize {
@@ -101,7 +101,7 @@ impl From<usize> for InfoToDataIdx {
 }
 
 #[repr(transparent)]
-#[derive(BinRead, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(BinRead, BinWrite, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct FileDataIdx(pub u32);
 
 impl From<FileDataIdx> for usize {
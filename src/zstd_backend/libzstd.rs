@@ -1,2 +1,11 @@
 pub use zstd::decode_all;
 pub use zstd::stream::copy_decode;
+
+use std::io::{BufReader, Read, Result};
+
+/// A reader which decompresses the data read from another reader
+pub type Decoder<R> = zstd::stream::read::Decoder<BufReader<R>>;
+
+pub fn encode_all<R: Read>(source: R) -> Result<Vec<u8>> {
+    zstd::encode_all(source, 0)
+}
@@ -16,20 +16,47 @@ compile_error!("Only one ZSTD backend can be enabled at a time");
 #[cfg(not(any(feature = "libzstd", feature = "rust-zstd", feature = "nozstd", doc)))]
 compile_error!("At least one ZSTD backend feature must be enabled");
 
+/// Without a zstd backend (the `nozstd` feature) compressed data can't be read or written, so
+/// every operation fails with [`ErrorKind::Unsupported`](std::io::ErrorKind::Unsupported)
 #[cfg(not(any(feature = "libzstd", feature = "rust-zstd")))]
 mod template {
-    use std::io::{Read, Result, Write};
+    use std::io::{Error, ErrorKind, Read, Result, Write};
+
+    fn unsupported() -> Error {
+        Error::new(
+            ErrorKind::Unsupported,
+            "zstd is unsupported, as no zstd backend feature is enabled",
+        )
+    }
 
     pub fn copy_decode<R, W>(mut _source: R, mut _destination: W) -> Result<()>
     where
         R: Read,
         W: Write,
     {
-        todo!()
+        Err(unsupported())
     }
 
     pub fn decode_all<R: Read>(mut _source: R) -> Result<Vec<u8>> {
-        todo!()
+        Err(unsupported())
+    }
+
+    pub fn encode_all<R: Read>(mut _source: R) -> Result<Vec<u8>> {
+        Err(unsupported())
+    }
+
+    pub struct Decoder<R>(R);
+
+    impl<R: Read> Decoder<R> {
+        pub fn new(_source: R) -> Result<Self> {
+            Err(unsupported())
+        }
+    }
+
+    impl<R: Read> Read for Decoder<R> {
+        fn read(&mut self, _buf: &mut [u8]) -> Result<usize> {
+            Err(unsupported())
+        }
     }
 }
 
@@ -1,4 +1,5 @@
 use ruzstd::streaming_decoder::StreamingDecoder;
+use ruzstd::{BlockDecodingStrategy, FrameDecoder};
 use std::io::{self, Error, ErrorKind, Read, Result, Write};
 
 pub fn copy_decode<R, W>(mut source: R, mut destination: W) -> Result<()>
@@ -21,3 +22,71 @@ pub fn decode_all<R: Read>(mut source: R) -> Result<Vec<u8>> {
 
     Ok(out)
 }
+
+/// A reader which decompresses the data read from another reader. Unlike [`StreamingDecoder`]
+/// this owns its source.
+pub struct Decoder<R> {
+    decoder: FrameDecoder,
+    source: R,
+}
+
+impl<R: Read> Decoder<R> {
+    pub fn new(mut source: R) -> Result<Self> {
+        let mut decoder = FrameDecoder::new();
+        decoder
+            .init(&mut source)
+            .map_err(|err| Error::new(ErrorKind::Other, err))?;
+
+        Ok(Self { decoder, source })
+    }
+}
+
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        // Same as StreamingDecoder::read, decode_blocks may decode fewer bytes than requested
+        while self.decoder.can_collect() < buf.len() && !self.decoder.is_finished() {
+            let needed = buf.len() - self.decoder.can_collect();
+            self.decoder
+                .decode_blocks(&mut self.source, BlockDecodingStrategy::UptoBytes(needed))
+                .map_err(|err| {
+                    Error::new(
+                        ErrorKind::Other,
+                        format!("Error in the zstd decoder: {:?}", err),
+                    )
+                })?;
+        }
+
+        self.decoder.read(buf)
+    }
+}
+
+const ZSTD_MAGIC: u32 = 0xFD2F_B528;
+const MAX_BLOCK_SIZE: usize = 0x2_0000;
+
+// ruzstd has no encoder, so emit a valid zstd frame made entirely of raw (stored) blocks
+pub fn encode_all<R: Read>(mut source: R) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    source.read_to_end(&mut data)?;
+
+    let mut out = Vec::with_capacity(data.len() + 13 + (data.len() / MAX_BLOCK_SIZE + 1) * 3);
+    out.extend_from_slice(&ZSTD_MAGIC.to_le_bytes());
+
+    // Single segment with an 8 byte frame content size, no checksum, no dictionary
+    out.push(0xE0);
+    out.extend_from_slice(&(data.len() as u64).to_le_bytes());
+
+    let mut blocks = data.chunks(MAX_BLOCK_SIZE).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0]);
+    }
+
+    while let Some(block) = blocks.next() {
+        let is_last = blocks.peek().is_none() as u32;
+        let header = is_last | ((block.len() as u32) << 3);
+
+        out.extend_from_slice(&header.to_le_bytes()[..3]);
+        out.extend_from_slice(block);
+    }
+
+    Ok(out)
+}